@@ -1,4 +1,87 @@
-// This is needed for the build to provide the the OUT_DIR build env flag
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Collects every file under `dir`, relative to `root`
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) {
+    for entry in fs::read_dir(dir).unwrap_or_else(|_| panic!("Unable to read {}", dir.display())) {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(root, &path, files);
+        } else {
+            let relative_path = path
+                .strip_prefix(root)
+                .unwrap()
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative_path, path.canonicalize().unwrap()));
+        }
+    }
+}
+
+/// Whether the crate is built with `cfg(debug_assertions)`. Cargo only tells build scripts this
+/// from 1.93, but before that they were still built with the same setting as the crate.
+fn debug_assertions() -> bool {
+    env::var_os("CARGO_CFG_DEBUG_ASSERTIONS").is_some() || cfg!(debug_assertions)
+}
+
+// This is also needed for the build to provide the the OUT_DIR build env flag. Debug builds serve
+// the web client from OUT_DIR while rebuilding it as it changes, but release builds embed it into
+// the binary so no npm is needed at runtime.
 fn main() {
     println!("cargo::rerun-if-changed=build.rs");
+    // The server embeds the client exactly when it was built here, whatever the profile
+    println!("cargo::rustc-check-cfg=cfg(embedded_client)");
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let assets_file = out_dir.join("static_assets.rs");
+    if debug_assertions() {
+        fs::write(
+            assets_file,
+            "pub const STATIC_ASSETS: &[(&str, &[u8])] = &[];\n",
+        )
+        .unwrap();
+        return;
+    }
+    println!("cargo::rustc-cfg=embedded_client");
+    println!("cargo::rerun-if-changed=src/web_actor/static");
+    println!("cargo::rerun-if-env-changed=CPD_PREBUILT_CLIENT");
+
+    // Allows packaging a client that was built separately, such as in CI
+    let client_dir = match env::var_os("CPD_PREBUILT_CLIENT") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let status = Command::new("npm")
+                .args(["run", "build-release"])
+                .env("OUT_DIR", &out_dir)
+                .status()
+                .expect("Unable to run npm. Is it installed?");
+            if !status.success() {
+                panic!("Failed to build the web client. Have you run `npm ci`?");
+            }
+            out_dir.join("static")
+        }
+    };
+
+    let mut files = vec![];
+    collect_files(&client_dir, &client_dir, &mut files);
+    files.sort();
+    let mut entries = String::new();
+    for (relative_path, path) in &files {
+        writeln!(
+            entries,
+            "    ({relative_path:?}, include_bytes!({path:?})),"
+        )
+        .unwrap();
+    }
+    fs::write(
+        assets_file,
+        format!("pub const STATIC_ASSETS: &[(&str, &[u8])] = &[\n{entries}];\n"),
+    )
+    .unwrap();
 }
@@ -2,7 +2,7 @@ use std::{fmt::Display, process::ExitCode};
 
 use crate::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     Pass,
     Act(CardId, CharacterId),
@@ -10,6 +10,6 @@ pub trait Actor: Sync {
     /// Perform action on turn
     async fn act(&self, battle: &Battle) -> ActionResult;
 
-    /// Called when the game is over
-    async fn on_game_over(&self, battle: &Battle);
+    /// Called when the game is over with how the battle was decided
+    async fn on_game_over(&self, battle: &Battle, outcome: &BattleOutcome);
 }
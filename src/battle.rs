@@ -1,19 +1,23 @@
 use crate::{
-    battle_file, battle_markup, Action, ActionError, Actor, Attack, BattleText, Board, BoardItem,
-    Card, CardAction, CardId, Character, CharacterId, DeclareWrappedType, GridLocation, Health,
-    RandomProvider, Target, U64Range,
+    battle_file, battle_markup, Action, ActionError, Actor, Attack, BattleObserver, BattleOutcome,
+    BattleText, BattleUpdate, Board, BoardItem, Card, CardAction, CardId, CardPickup, Character,
+    CharacterId, DeadBody, DeathRule, DeclareWrappedType, DrawReason, GridLocation, Health,
+    HistoryEntry, Objective, RandomProvider, Target, TurnOrder, U64Range, Victory,
+    TIMELINE_TICKS_PER_ROUND, TIMELINE_TURN_COST,
 };
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::process::ExitCode;
+use ts_rs::TS;
 
 DeclareWrappedType!(TeamId, id, u64);
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, TS)]
 pub struct Team {
     pub id: TeamId,
     pub name: String,
+    pub objectives: Vec<Objective>,
 }
 
 #[derive(Debug)]
@@ -23,22 +27,31 @@ struct Turn {
 
 type StoryCard = battle_file::StoryCard;
 
-#[derive(Serialize)]
+/// The full state of a battle, including every character's cards. Clients only ever receive a
+/// [`crate::BattleView`] of it.
 pub struct Battle {
-    #[serde(skip)]
     pub actors: Vec<(TeamId, Box<dyn Actor>)>,
     pub characters: HashMap<CharacterId, Character>,
     pub introduction: Option<StoryCard>,
     pub teams: Vec<Team>,
-    pub history: Vec<BattleText>,
-    #[serde(skip)]
+    pub history: Vec<HistoryEntry>,
     pub random_provider: Box<dyn RandomProvider>,
     pub round: u16,
     pub cards: HashMap<CardId, Card>,
     pub default_turn_actions: u64,
-    #[serde(skip)]
+    pub card_pickup: CardPickup,
+    pub death_rule: DeathRule,
     pub asset_directory: Option<PathBuf>,
     pub board: Board,
+    pub max_rounds: Option<u16>,
+    /// A value of 0 disables stalemate detection
+    pub stalemate_rounds: u16,
+    pub rounds_without_health_change: u16,
+    pub outcome: Option<BattleOutcome>,
+    pub turn_order_rule: TurnOrder,
+    /// The characters still to act this round, starting with the current one
+    pub turn_order: Vec<CharacterId>,
+    pub observers: Vec<Box<dyn BattleObserver>>,
 }
 
 unsafe impl Sync for Battle {}
@@ -57,22 +70,165 @@ impl Battle {
         None
     }
 
-    pub fn get_team_from_id(&self, id: TeamId) -> Option<&Team> {
-        self.teams.iter().find(|&team| team.id == id)
+    pub fn get_team_for_character(&self, character_id: &CharacterId) -> Option<TeamId> {
+        self.actors
+            .iter()
+            .find(|(_, actor)| actor.get_character_id() == character_id)
+            .map(|(team_id, _)| *team_id)
     }
 
-    fn build_turns(&self) -> Vec<Turn> {
-        let mut ret = vec![];
-        for (_team_id, actor) in &self.actors {
-            if self.get_character(actor.as_ref()).is_dead() {
+    /// Every location the living members of a team can currently see, or `None` if nothing is
+    /// hidden from the team because one of them has unlimited vision.
+    pub fn get_visible_locations(&self, team_id: TeamId) -> Option<HashSet<GridLocation>> {
+        let mut visible = HashSet::new();
+        for character in self.get_team_members(team_id) {
+            if character.is_dead() {
                 continue;
             }
+            character.vision?;
+            if let Some((x, y)) = self.board.find(&BoardItem::Character(character.id)) {
+                visible.extend(
+                    self.board
+                        .find_visible(&GridLocation { x, y }, character.vision),
+                );
+            }
+        }
+        Some(visible)
+    }
+
+    /// Whether a team can see a character. Teammates are always visible to each other.
+    pub fn is_visible_to(&self, team_id: TeamId, character_id: &CharacterId) -> bool {
+        self.is_visible_in(
+            team_id,
+            character_id,
+            self.get_visible_locations(team_id).as_ref(),
+        )
+    }
 
-            ret.push(Turn {
-                character: *actor.get_character_id(),
-            });
+    /// Whether a team can see a character, given what [`Self::get_visible_locations`] found the
+    /// team can see. Saves finding it again when checking many characters.
+    pub fn is_visible_in(
+        &self,
+        team_id: TeamId,
+        character_id: &CharacterId,
+        visible: Option<&HashSet<GridLocation>>,
+    ) -> bool {
+        if self.get_team_for_character(character_id) == Some(team_id) {
+            return true;
         }
-        ret
+        match visible {
+            None => true,
+            Some(visible) => self
+                .board
+                .find(&BoardItem::Character(*character_id))
+                .or_else(|| self.board.find(&BoardItem::Remains(*character_id)))
+                .is_some_and(|(x, y)| visible.contains(&GridLocation { x, y })),
+        }
+    }
+
+    /// What each team still in the battle can currently see, as found by
+    /// [`Self::get_visible_locations`]
+    pub fn get_visible_locations_by_team(&self) -> Vec<(TeamId, Option<HashSet<GridLocation>>)> {
+        self.teams
+            .iter()
+            .filter(|team| !self.is_team_defeated(team))
+            .map(|team| (team.id, self.get_visible_locations(team.id)))
+            .collect()
+    }
+
+    /// Records something that happened, hiding it from the teams that can't currently see every
+    /// character it mentions
+    fn record_history(&mut self, text: BattleText, characters: &[CharacterId]) {
+        let hidden_from = self.hidden_from(characters);
+        self.history.push(HistoryEntry { text, hidden_from });
+    }
+
+    fn hidden_from(&self, characters: &[CharacterId]) -> Vec<TeamId> {
+        self.teams
+            .iter()
+            .map(|team| team.id)
+            .filter(|team_id| {
+                let visible = self.get_visible_locations(*team_id);
+                !characters.iter().all(|character_id| {
+                    self.is_visible_in(*team_id, character_id, visible.as_ref())
+                })
+            })
+            .collect()
+    }
+
+    pub fn get_team_from_id(&self, id: TeamId) -> Option<&Team> {
+        self.teams.iter().find(|&team| team.id == id)
+    }
+
+    fn build_turns(&mut self) -> Vec<Turn> {
+        let living: Vec<(TeamId, CharacterId)> = self
+            .actors
+            .iter()
+            .filter(|(_, actor)| !self.get_character(actor.as_ref()).is_dead())
+            .map(|(team_id, actor)| (*team_id, *actor.get_character_id()))
+            .collect();
+
+        let order: Vec<CharacterId> = match &self.turn_order_rule {
+            TurnOrder::Sequential => living.iter().map(|(_, id)| *id).collect(),
+            TurnOrder::Initiative(roll) => {
+                let mut rolls: Vec<(u64, CharacterId)> = living
+                    .iter()
+                    .map(|(_, id)| {
+                        let speed = self.characters[id].speed;
+                        (speed + roll.resolve(self.random_provider.as_ref()), *id)
+                    })
+                    .collect();
+                // Stable so ties keep the listed order
+                rolls.sort_by(|a, b| b.0.cmp(&a.0));
+                rolls.into_iter().map(|(_, id)| id).collect()
+            }
+            TurnOrder::TeamAlternating => {
+                let mut queues: Vec<Vec<CharacterId>> = self
+                    .teams
+                    .iter()
+                    .map(|team| {
+                        living
+                            .iter()
+                            .filter(|(team_id, _)| *team_id == team.id)
+                            .map(|(_, id)| *id)
+                            .collect()
+                    })
+                    .collect();
+                if !queues.is_empty() {
+                    let leader = usize::from(self.round.saturating_sub(1)) % queues.len();
+                    queues.rotate_left(leader);
+                }
+                let mut order = vec![];
+                for index in 0..queues.iter().map(Vec::len).max().unwrap_or(0) {
+                    order.extend(queues.iter().filter_map(|queue| queue.get(index)));
+                }
+                order
+            }
+            TurnOrder::Timeline => {
+                let mut order = vec![];
+                for _ in 0..TIMELINE_TICKS_PER_ROUND {
+                    let mut ready = vec![];
+                    for (_, id) in &living {
+                        let character = self.characters.get_mut(id).unwrap();
+                        // Characters without any speed would otherwise never act
+                        character.action_points += character.speed.max(1);
+                        if character.action_points >= TIMELINE_TURN_COST {
+                            ready.push((character.action_points, *id));
+                        }
+                    }
+                    ready.sort_by(|a, b| b.0.cmp(&a.0));
+                    for (_, id) in ready {
+                        self.characters.get_mut(&id).unwrap().action_points -= TIMELINE_TURN_COST;
+                        order.push(id);
+                    }
+                }
+                order
+            }
+        };
+        order
+            .into_iter()
+            .map(|character| Turn { character })
+            .collect()
     }
 
     pub fn get_actor(&self, character_id: &CharacterId) -> Option<&dyn Actor> {
@@ -117,6 +273,257 @@ impl Battle {
         cur_id
     }
 
+    fn pick_up_card(&mut self, character_id: CharacterId, card_id: CardId) {
+        let character = self.characters.get_mut(&character_id).unwrap();
+        match self.card_pickup {
+            CardPickup::Deck => character.deck.push(card_id),
+            CardPickup::Hand => character.hand.push(card_id),
+        }
+        let text = battle_markup![
+            @id(&character.name),
+            " picked up ",
+            @attack(&self.cards[&card_id].name),
+        ];
+        self.record_history(text, &[character_id]);
+    }
+
+    /// Applies the death rule to a character that was just defeated and scatters their loot
+    fn on_defeated(&mut self, character_id: CharacterId) -> BattleText {
+        let mut history_entry = BattleText::new();
+        let Some((x, y)) = self.board.find(&BoardItem::Character(character_id)) else {
+            return history_entry;
+        };
+        match self.death_rule.body {
+            DeadBody::Remove => {
+                self.board.grid.clear(x, y);
+            }
+            DeadBody::Corpse => {}
+            DeadBody::Remains => {
+                self.board.grid.set(x, y, BoardItem::Remains(character_id));
+            }
+        }
+
+        let character = self.characters.get_mut(&character_id).unwrap();
+        for card_id in std::mem::take(&mut character.loot) {
+            let Some(location) = self.board.find_nearest_empty(&GridLocation { x, y }) else {
+                break;
+            };
+            self.board
+                .grid
+                .set(location.x, location.y, BoardItem::Card(card_id));
+            history_entry.extend(battle_markup![
+                @id(&character.name),
+                " dropped ",
+                @attack(&self.cards[&card_id].name),
+                ". ",
+            ]);
+        }
+        history_entry
+    }
+
+    fn get_team_members(&self, team_id: TeamId) -> impl Iterator<Item = &Character> {
+        self.actors
+            .iter()
+            .filter(move |(id, _)| *id == team_id)
+            .map(|(_, actor)| self.get_character(actor.as_ref()))
+    }
+
+    /// A team is defeated once all of its members are dead or a character it protects falls
+    pub fn is_team_defeated(&self, team: &Team) -> bool {
+        team.objectives.iter().any(|objective| {
+            matches!(objective, Objective::Protect(id) if self.characters[id].is_dead())
+        }) || self.get_team_members(team.id).all(Character::is_dead)
+    }
+
+    fn is_objective_met(&self, team: &Team, objective: &Objective, round_complete: bool) -> bool {
+        match objective {
+            Objective::EliminateAll => self
+                .teams
+                .iter()
+                .filter(|other| other.id != team.id)
+                .all(|other| self.is_team_defeated(other)),
+            Objective::Defeat(id) => self.characters[id].is_dead(),
+            Objective::Survive(rounds) => round_complete && self.round >= *rounds,
+            Objective::ReachTile(location) => self.get_team_members(team.id).any(|character| {
+                !character.is_dead()
+                    && self.board.find(&BoardItem::Character(character.id))
+                        == Some((location.x, location.y))
+            }),
+            Objective::Protect(_) => false,
+        }
+    }
+
+    /// Checks the objectives of every team still in the battle and returns the first one achieved.
+    /// Objectives that are only evaluated between rounds are skipped unless `round_complete` is set.
+    pub fn check_objectives(&self, round_complete: bool) -> Option<Victory> {
+        self.teams
+            .iter()
+            .filter(|team| !self.is_team_defeated(team))
+            .find_map(|team| {
+                team.objectives
+                    .iter()
+                    .find(|objective| self.is_objective_met(team, objective, round_complete))
+                    .map(|objective| Victory {
+                        team: team.id,
+                        objective: objective.clone(),
+                    })
+            })
+    }
+
+    /// Decides whether the battle is over. Round limits and stalemates are only checked once
+    /// `round_complete` is set.
+    pub fn check_outcome(&self, round_complete: bool) -> Option<BattleOutcome> {
+        if let Some(victory) = self.check_objectives(round_complete) {
+            return Some(BattleOutcome::Victory(victory));
+        }
+        if self.teams.iter().all(|team| self.is_team_defeated(team)) {
+            return Some(BattleOutcome::Draw(DrawReason::MutualDefeat));
+        }
+        if round_complete {
+            if let Some(max_rounds) = self.max_rounds.filter(|max| self.round >= *max) {
+                return Some(BattleOutcome::Draw(DrawReason::RoundLimit(max_rounds)));
+            }
+            if self.stalemate_rounds > 0
+                && self.rounds_without_health_change >= self.stalemate_rounds
+            {
+                return Some(BattleOutcome::Draw(DrawReason::Stalemate(
+                    self.rounds_without_health_change,
+                )));
+            }
+            if !self.can_win_without_fighting() && !self.can_any_enemies_meet() {
+                return Some(BattleOutcome::Draw(DrawReason::Unreachable));
+            }
+        }
+        None
+    }
+
+    /// Whether a team could still win without hurting anyone, by surviving or reaching a tile
+    fn can_win_without_fighting(&self) -> bool {
+        self.teams
+            .iter()
+            .filter(|team| !self.is_team_defeated(team))
+            .flat_map(|team| &team.objectives)
+            .any(|objective| matches!(objective, Objective::Survive(_) | Objective::ReachTile(_)))
+    }
+
+    /// Whether any living character could ever damage a living enemy, given the walls between them.
+    /// Any card may end up in anyone's hand and characters may get out of each other's way, so only
+    /// walls and the longest reach of any card keep characters apart.
+    fn can_any_enemies_meet(&self) -> bool {
+        let reach = self
+            .cards
+            .values()
+            .flat_map(|card| {
+                card.actions.iter().filter_map(|action| match action {
+                    CardAction::Damage { area, .. } => Some(card.range.saturating_add(area.1)),
+                    _ => None,
+                })
+            })
+            .max();
+        let Some(reach) = reach else {
+            return false;
+        };
+
+        let (width, height) = (self.board.grid.width(), self.board.grid.height());
+        // Labels every tile with the area of the board it can be walked to from
+        let mut areas: Vec<Vec<Option<usize>>> = vec![vec![None; width]; height];
+        let mut area_tiles: Vec<Vec<GridLocation>> = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                if areas[y][x].is_some() || self.board.grid.get(x, y) == Some(&BoardItem::Wall) {
+                    continue;
+                }
+                let area = area_tiles.len();
+                let mut tiles = vec![GridLocation { x, y }];
+                areas[y][x] = Some(area);
+                let mut next = 0;
+                while let Some(tile) = tiles.get(next).cloned() {
+                    next += 1;
+                    for neighbor in tile.get_surrounding(width, height) {
+                        if areas[neighbor.y][neighbor.x].is_none()
+                            && self.board.grid.get(neighbor.x, neighbor.y) != Some(&BoardItem::Wall)
+                        {
+                            areas[neighbor.y][neighbor.x] = Some(area);
+                            tiles.push(neighbor);
+                        }
+                    }
+                }
+                area_tiles.push(tiles);
+            }
+        }
+
+        let team_areas: Vec<(TeamId, HashSet<usize>)> = self
+            .teams
+            .iter()
+            .map(|team| {
+                let team_areas = self
+                    .get_team_members(team.id)
+                    .filter(|character| !character.is_dead())
+                    .filter_map(|character| self.board.find(&BoardItem::Character(character.id)))
+                    .filter_map(|(x, y)| areas[y][x])
+                    .collect();
+                (team.id, team_areas)
+            })
+            .collect();
+        let areas_meet = |a: usize, b: usize| {
+            a == b
+                || area_tiles[a].iter().any(|tile_a| {
+                    area_tiles[b]
+                        .iter()
+                        .any(|tile_b| tile_a.distance(tile_b) as u64 <= reach)
+                })
+        };
+        team_areas.iter().any(|(team_id, areas)| {
+            team_areas
+                .iter()
+                .filter(|(other_id, _)| other_id != team_id)
+                .any(|(_, other_areas)| {
+                    areas
+                        .iter()
+                        .any(|a| other_areas.iter().any(|b| areas_meet(*a, *b)))
+                })
+        })
+    }
+
+    fn get_health_snapshot(&self) -> HashMap<CharacterId, Health> {
+        self.characters
+            .iter()
+            .map(|(id, character)| (*id, character.health))
+            .collect()
+    }
+
+    pub fn describe_outcome(&self, outcome: &BattleOutcome) -> String {
+        match outcome {
+            BattleOutcome::Victory(victory) => format!(
+                "{} won. {}.",
+                self.get_team_from_id(victory.team).unwrap().name,
+                self.describe_objective(&victory.objective)
+            ),
+            BattleOutcome::Draw(DrawReason::MutualDefeat) => {
+                "Draw. Every team was defeated.".to_string()
+            }
+            BattleOutcome::Draw(DrawReason::RoundLimit(rounds)) => {
+                format!("Draw. The limit of {rounds} rounds was reached.")
+            }
+            BattleOutcome::Draw(DrawReason::Stalemate(rounds)) => {
+                format!("Draw. No one was hurt or healed for {rounds} rounds.")
+            }
+            BattleOutcome::Draw(DrawReason::Unreachable) => {
+                "Draw. No one could reach an enemy.".to_string()
+            }
+        }
+    }
+
+    pub fn describe_objective(&self, objective: &Objective) -> String {
+        match objective {
+            Objective::EliminateAll => "Every opposing team was defeated".to_string(),
+            Objective::Defeat(id) => format!("{} was defeated", self.characters[id].name),
+            Objective::Survive(rounds) => format!("Survived {rounds} rounds"),
+            Objective::ReachTile(location) => format!("Reached {}, {}", location.x, location.y),
+            Objective::Protect(id) => format!("{} must survive", self.characters[id].name),
+        }
+    }
+
     fn get_all_character_amounts_in_range(
         &self,
         target_id: CharacterId,
@@ -124,7 +531,14 @@ impl Battle {
         amount: &U64Range,
     ) -> Vec<(CharacterId, u64)> {
         let range = area.resolve(self.random_provider.as_ref());
-        let (attack_x, attack_y) = self.board.find(&BoardItem::Character(target_id)).unwrap();
+        let Some((attack_x, attack_y)) = self
+            .board
+            .find(&BoardItem::Character(target_id))
+            .or_else(|| self.board.find(&BoardItem::Remains(target_id)))
+        else {
+            // The target may have been removed from the board by an earlier action
+            return vec![];
+        };
 
         self.get_characters_in_range(
             GridLocation {
@@ -134,6 +548,7 @@ impl Battle {
             range,
         )
         .iter()
+        .filter(|id| self.death_rule.area_effects_hit_dead || !self.characters[id].is_dead())
         .map(|id| (*id, amount.resolve(self.random_provider.as_ref())))
         .collect()
     }
@@ -158,15 +573,132 @@ impl Battle {
             .find_chars_in_range(location, range.try_into().unwrap())
     }
 
+    /// Checks whether `actor` may take `action` right now, explaining why not if they can't
+    pub fn check_action(&self, actor: &CharacterId, action: &Action) -> Result<(), String> {
+        let Some(character) = self.characters.get(actor) else {
+            return Err(format!("Unknown character {actor}"));
+        };
+        if character.is_dead() {
+            return Err(format!("{} has been defeated", character.name));
+        }
+        match action {
+            Action::Pass => Ok(()),
+            Action::Move(target, location) => {
+                if actor != target {
+                    return Err("Characters can only move themselves".into());
+                }
+                if character.movement == 0 {
+                    return Err(format!("{} can't move any further", character.name));
+                }
+                let Some((x, y)) = self.board.find(&BoardItem::Character(*target)) else {
+                    return Err(format!("{} isn't on the board", character.name));
+                };
+                if !location.is_adjacent(&GridLocation { x, y })
+                    || !self.board.grid.is_valid(location.x, location.y)
+                {
+                    return Err("Characters can only move to an adjacent tile".into());
+                }
+                if !self
+                    .board
+                    .grid
+                    .get(location.x, location.y)
+                    .map_or(true, BoardItem::is_passable)
+                {
+                    return Err("That tile is blocked".into());
+                }
+                Ok(())
+            }
+            Action::Act(card_id, target_id) => {
+                let Some(card) = self.cards.get(card_id) else {
+                    return Err(format!("Unknown card {card_id}"));
+                };
+                if !character.hand.contains(card_id) {
+                    return Err(format!("{} isn't in {}'s hand", card.name, character.name));
+                }
+                // Characters out of sight are treated as if they don't exist to avoid revealing them
+                let target_character = self.characters.get(target_id).filter(|_| {
+                    self.get_team_for_character(actor)
+                        .map_or(true, |team_id| self.is_visible_to(team_id, target_id))
+                });
+                let Some(target_character) = target_character else {
+                    return Err(format!("Unknown target {target_id}"));
+                };
+
+                let actual_target = if actor == target_id {
+                    Target::Me
+                } else {
+                    Target::Others
+                };
+                if !card.target().is_super_set(&actual_target) {
+                    return Err(format!(
+                        "{} can't be used on {}",
+                        card.name, target_character.name
+                    ));
+                }
+                if target_character.is_dead() {
+                    return Err(format!("{} has been defeated", target_character.name));
+                }
+                if !self.is_in_range(card.range, *actor, *target_id) {
+                    return Err(format!("{} is out of range", target_character.name));
+                }
+                if character.remaining_actions == 0 {
+                    return Err(format!("{} has no actions left", character.name));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Every action `character_id` could take right now, which is none unless it's their turn
+    pub fn legal_actions(&self, character_id: &CharacterId) -> Vec<Action> {
+        if self.outcome.is_some() || self.turn_order.first() != Some(character_id) {
+            return vec![];
+        }
+        let Some(character) = self.characters.get(character_id) else {
+            return vec![];
+        };
+
+        let mut candidates = vec![Action::Pass];
+        if let Some((x, y)) = self.board.find(&BoardItem::Character(*character_id)) {
+            candidates.extend(
+                GridLocation { x, y }
+                    .get_surrounding(self.board.grid.width(), self.board.grid.height())
+                    .into_iter()
+                    .map(|location| Action::Move(*character_id, location)),
+            );
+        }
+        let mut cards = character.hand.clone();
+        cards.sort();
+        cards.dedup();
+        let mut targets: Vec<&CharacterId> = self.characters.keys().collect();
+        targets.sort();
+        for card_id in cards {
+            candidates.extend(
+                targets
+                    .iter()
+                    .map(|target_id| Action::Act(card_id, **target_id)),
+            );
+        }
+
+        candidates
+            .into_iter()
+            .filter(|action| self.check_action(character_id, action).is_ok())
+            .collect()
+    }
+
     /// Attempts to carry out the action. If the action (legal or no) consumes an action, returns true
     fn handle_action(&mut self, actor: &CharacterId, action: Action) -> bool {
+        if self.check_action(actor, &action).is_err() {
+            return false;
+        }
         let character = &self.characters[actor];
         match action {
             Action::Pass => {
-                self.history.push(battle_markup![
+                let text = battle_markup![
                     @id(&character.name),
                     " took no action",
-                ]);
+                ];
+                self.record_history(text, &[*actor]);
                 let character = self.characters.get_mut(actor).unwrap();
                 character.remaining_actions = 0;
                 character.movement = 0;
@@ -174,50 +706,22 @@ impl Battle {
                 true
             }
             Action::Move(target, location) => {
-                if actor != &target || character.movement == 0 {
-                    return false;
-                }
+                let (x, y) = self.board.find(&BoardItem::Character(target)).unwrap();
+                self.characters.get_mut(&target).unwrap().movement -= 1;
 
-                if let Some((x, y)) = self.board.find(&BoardItem::Character(target)) {
-                    if location.is_adjacent(&GridLocation { x, y })
-                        && !self.board.grid.is_set(location.x, location.y)
-                    {
-                        self.characters.get_mut(&target).unwrap().movement -= 1;
-
-                        self.board.grid.clear(x, y);
-                        self.board
-                            .grid
-                            .set(location.x, location.y, BoardItem::Character(target));
-                        return true;
-                    }
+                self.board.grid.clear(x, y);
+                if let Some(BoardItem::Card(card_id)) =
+                    self.board
+                        .grid
+                        .set(location.x, location.y, BoardItem::Character(target))
+                {
+                    self.pick_up_card(target, card_id);
                 }
-
-                false
+                true
             }
             Action::Act(card_id, target_id) => {
                 let card = &self.cards[&card_id];
-                let actual_target = if *actor == target_id {
-                    Target::Me
-                } else {
-                    Target::Others
-                };
-
-                if !card.target().is_super_set(&actual_target) {
-                    return false;
-                }
-
                 let target_character = &self.characters[&target_id];
-                if target_character.is_dead() {
-                    return false;
-                }
-
-                if !self.is_in_range(card.range, *actor, target_id) {
-                    return false;
-                }
-
-                if character.remaining_actions == 0 {
-                    return false;
-                }
 
                 let mut history_entry = battle_markup![
                     @id(&character.name),
@@ -230,6 +734,8 @@ impl Battle {
 
                 self.characters.get_mut(actor).unwrap().remaining_actions -= 1;
 
+                let mut mentioned = vec![*actor, target_id];
+                let mut defeated = vec![];
                 for action in &card.actions {
                     // If the action specifically targets me, then force it to target the actor
                     // rather than the potentially other target.
@@ -249,7 +755,12 @@ impl Battle {
                                     self.characters.get_mut(&attacked_character_id).unwrap();
 
                                 history_entry.extend(battle_markup![@damage(&value), " damage to ", @id(&attacked_character.name), ". " ]);
+                                mentioned.push(attacked_character_id);
+                                let was_dead = attacked_character.is_dead();
                                 attacked_character.health -= Attack::new(value);
+                                if !was_dead && attacked_character.is_dead() {
+                                    defeated.push(attacked_character_id);
+                                }
                             }
                         }
                         CardAction::Heal { amount, area, .. } => {
@@ -258,6 +769,11 @@ impl Battle {
                             {
                                 let healed_character =
                                     self.characters.get_mut(&healed_character_id).unwrap();
+                                // Healing doesn't revive the dead, who no longer have a place on
+                                // the board to act from
+                                if healed_character.is_dead() {
+                                    continue;
+                                }
 
                                 history_entry
                                     .extend(battle_markup!["Healed ", @damage(&value), ". "]);
@@ -283,7 +799,16 @@ impl Battle {
                     }
                 }
 
-                self.history.push(history_entry);
+                // Decided before any bodies are removed from the board
+                let hidden_from = self.hidden_from(&mentioned);
+                for character_id in defeated {
+                    history_entry.extend(self.on_defeated(character_id));
+                }
+
+                self.history.push(HistoryEntry {
+                    text: history_entry,
+                    hidden_from,
+                });
 
                 // Remove card from hand
                 let hand = &mut self.characters.get_mut(actor).unwrap().hand;
@@ -297,28 +822,46 @@ impl Battle {
     pub async fn advance(&mut self) -> Result<(), ExitCode> {
         self.round += 1;
         self.history
-            .push(battle_markup![format!("--- Round {}", self.round)]);
+            .push(HistoryEntry::public(battle_markup![format!(
+                "--- Round {}",
+                self.round
+            )]));
+        let health_before = self.get_health_snapshot();
         let turns = self.build_turns();
+        self.turn_order = turns.iter().map(|turn| turn.character).collect();
         for turn in turns {
             let character = self.characters.get_mut(&turn.character).unwrap();
+            if character.is_dead() {
+                self.turn_order.remove(0);
+                continue;
+            }
+
             character.reset_hand(self.random_provider.as_ref());
             character.remaining_actions = character
                 .get_default_turn_actions()
                 .unwrap_or(self.default_turn_actions);
             character.movement = character.default_movement;
+            self.notify_observers(&BattleUpdate::TurnStarted(turn.character))
+                .await;
 
-            if character.is_dead() {
-                continue;
-            }
-
-            while self.characters[&turn.character].remaining_actions > 0
-                || self.characters[&turn.character].movement > 0
+            while !self.characters[&turn.character].is_dead()
+                && (self.characters[&turn.character].remaining_actions > 0
+                    || self.characters[&turn.character].movement > 0)
             {
                 let actor: &dyn Actor = self.require_actor(&turn.character);
                 let action_result = actor.act(self).await;
                 match action_result {
                     Ok(request) => {
-                        self.handle_action(&turn.character, request);
+                        let update = BattleUpdate::ActionTaken(turn.character, request.clone());
+                        if self.handle_action(&turn.character, request) {
+                            self.notify_observers(&update).await;
+                        }
+                        if let Some(outcome) = self.check_outcome(false) {
+                            self.outcome = Some(outcome);
+                            // No one else gets a turn once the battle is over
+                            self.turn_order.clear();
+                            return Ok(());
+                        }
                     }
                     Err(ActionError::Failure(failure)) => {
                         println!("Error processing {}: {}", turn.character, failure.message);
@@ -328,39 +871,54 @@ impl Battle {
                     }
                 }
             }
-            if self.check_only_one_team_alive().is_some() {
-                return Ok(());
-            }
+            self.turn_order.remove(0);
+        }
+        if self.get_health_snapshot() == health_before {
+            self.rounds_without_health_change += 1;
+        } else {
+            self.rounds_without_health_change = 0;
         }
+        self.outcome = self.check_outcome(true);
         Ok(())
     }
 
     pub async fn run_to_completion(&mut self) -> Result<(), ExitCode> {
-        let mut surviving_team = None;
-        while surviving_team.is_none() {
+        while self.outcome.is_none() {
             self.advance().await?;
-            surviving_team = self.check_only_one_team_alive()
         }
-        let team_id = surviving_team.unwrap();
-        let team = self.get_team_from_id(team_id).unwrap();
-        self.history
-            .push(battle_markup![format!("{} won.", team.name)]);
+        let outcome = self.outcome.clone().unwrap();
+        self.history.push(HistoryEntry::public(battle_markup![
+            self.describe_outcome(&outcome)
+        ]));
 
         for (_, actor) in &self.actors {
-            actor.on_game_over(self).await;
+            actor.on_game_over(self, &outcome).await;
         }
+        self.notify_observers(&BattleUpdate::GameOver).await;
         Ok(())
     }
+
+    async fn notify_observers(&self, update: &BattleUpdate) {
+        for observer in &self.observers {
+            observer.on_update(self, update).await;
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use async_trait::async_trait;
     use futures::executor::block_on;
+    use std::sync::{Arc, Mutex};
 
-    use crate::{Battle, DefaultRandomProvider};
+    use crate::{
+        Action, Battle, BattleFormat, BattleObserver, BattleOutcome, BattleUpdate, BoardItem,
+        CardId, CharacterId, DefaultRandomProvider, Diagnostics, DrawReason, GridLocation, Health,
+        Objective, TeamId, Victory,
+    };
 
     #[tokio::test]
-    async fn test_deserialize() -> Result<(), String> {
+    async fn test_deserialize() -> Result<(), Diagnostics> {
         let battle_json = r#"{
             "title": "Example Game",
             "description": "Example Description",
@@ -368,7 +926,7 @@ mod tests {
             "board": { "width": 2, "height": 2 },
             "cards": [
                 {
-                    "id": 0,
+                    "id": "kick",
                     "name": "Kick",
                     "description": "Deal 123 damage",
                     "range": 1,
@@ -381,7 +939,7 @@ mod tests {
                     ]
                 },
                 {
-                    "id": 1,
+                    "id": "punch",
                     "name": "Punch",
                     "description": "Deal 456 damage",
                     "range": 888,
@@ -402,7 +960,7 @@ mod tests {
                             "name": "Member A1",
                             "race": "Human",
                             "base_health": 5,
-                            "cards": [0],
+                            "cards": ["kick"],
                             "hand_size": 1,
                             "location": [0, 0]
                         },
@@ -410,7 +968,7 @@ mod tests {
                             "name": "Member A2",
                             "race": "Human",
                             "base_health": 5,
-                            "cards": [1],
+                            "cards": ["punch"],
                             "location": [0, 1]
                         }
                     ]
@@ -422,15 +980,20 @@ mod tests {
                             "name": "Member B1",
                             "race": "Human",
                             "base_health": 15,
-                            "cards": [0],
+                            "cards": ["kick"],
                             "location": [1, 0]
                         }
                     ]
                 }
             ]
         }"#;
-        let mut battle =
-            Battle::deserialize(battle_json, None, Box::<DefaultRandomProvider>::default()).await?;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
         assert_eq!(battle.history.len(), 0);
         assert_eq!(battle.teams.len(), 2);
         assert_eq!(battle.teams[0].name, "Team A".to_string());
@@ -463,6 +1026,1019 @@ mod tests {
         );
 
         block_on(battle.run_to_completion()).unwrap();
+        assert_eq!(
+            battle.turn_order,
+            vec![],
+            "No one is left to act once the battle ends mid-round"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pickup_and_loot() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "default_movement": 1,
+            "board": { "width": 3, "height": 2 },
+            "cards": [
+                {
+                    "id": "smash",
+                    "name": "Smash",
+                    "description": "Deal 10 damage",
+                    "range": 1,
+                    "actions": [
+                        {
+                            "type": "damage",
+                            "target": "others",
+                            "amount": 10
+                        }
+                    ]
+                },
+                {
+                    "id": "treasure",
+                    "name": "Treasure",
+                    "description": "Shiny",
+                    "actions": []
+                }
+            ],
+            "pickups": [{ "card": "treasure", "location": [1, 0] }],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["smash"],
+                            "location": [0, 0]
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["smash"],
+                            "location": [2, 0],
+                            "loot": ["smash"]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let member_b = CharacterId::new(1);
+        battle.characters.get_mut(&member_a).unwrap().movement = 1;
+        battle
+            .characters
+            .get_mut(&member_a)
+            .unwrap()
+            .remaining_actions = 1;
+        battle.characters.get_mut(&member_a).unwrap().hand = vec![CardId::new(0)];
+
+        assert!(battle.handle_action(
+            &member_a,
+            Action::Move(member_a, GridLocation { x: 1, y: 0 })
+        ));
+        assert_eq!(
+            battle.characters[&member_a].deck,
+            vec![CardId::new(0), CardId::new(1)]
+        );
+        assert_eq!(battle.board.find(&BoardItem::Card(CardId::new(1))), None);
+
+        assert!(battle.handle_action(&member_a, Action::Act(CardId::new(0), member_b)));
+        assert!(battle.characters[&member_b].is_dead());
+        assert_eq!(
+            battle.board.find(&BoardItem::Card(CardId::new(0))),
+            Some((2, 1)),
+            "Loot lands on the closest empty tile"
+        );
+        assert!(battle.characters[&member_b].loot.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_death_rule_remains() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 2,
+            "board": { "width": 3, "height": 2 },
+            "dead_characters": { "body": "remains", "area_effects_hit_dead": false },
+            "cards": [
+                {
+                    "id": "smash",
+                    "name": "Smash",
+                    "description": "Deal 10 damage",
+                    "range": 1,
+                    "actions": [{ "type": "damage", "target": "others", "amount": 10 }]
+                },
+                {
+                    "id": "mend",
+                    "name": "Mend",
+                    "description": "Heal 5 nearby",
+                    "range": 2,
+                    "actions": [{ "type": "heal", "target": "others", "amount": 5, "area": 1 }]
+                }
+            ],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["smash", "mend"],
+                            "location": [0, 0]
+                        },
+                        {
+                            "name": "Member A2",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["smash"],
+                            "location": [0, 1]
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B1",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["smash"],
+                            "location": [1, 0]
+                        },
+                        {
+                            "name": "Member B2",
+                            "race": "Human",
+                            "base_health": 5,
+                            "max_health": 20,
+                            "cards": ["smash"],
+                            "location": [2, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let member_b1 = CharacterId::new(2);
+        let member_b2 = CharacterId::new(3);
+        let character = battle.characters.get_mut(&member_a).unwrap();
+        character.remaining_actions = 2;
+        character.movement = 1;
+        character.hand = vec![CardId::new(0), CardId::new(1)];
+
+        assert!(battle.handle_action(&member_a, Action::Act(CardId::new(0), member_b1)));
+        assert_eq!(
+            battle.board.grid.get(1, 0),
+            Some(&BoardItem::Remains(member_b1))
+        );
+
+        assert!(battle.handle_action(&member_a, Action::Act(CardId::new(1), member_b2)));
+        assert_eq!(battle.characters[&member_b2].health.health, 10);
+        assert!(
+            battle.characters[&member_b1].is_dead(),
+            "Area effects should skip the dead"
+        );
+
+        assert!(
+            battle.handle_action(
+                &member_a,
+                Action::Move(member_a, GridLocation { x: 1, y: 0 })
+            ),
+            "Remains are passable"
+        );
+        assert!(
+            !battle.handle_action(&member_b1, Action::Pass),
+            "The dead cannot act"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_heal_skips_dead() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 2,
+            "board": { "width": 3, "height": 1 },
+            "dead_characters": { "body": "remains" },
+            "cards": [
+                {
+                    "id": "smash",
+                    "name": "Smash",
+                    "description": "Deal 10 damage",
+                    "range": 1,
+                    "actions": [{ "type": "damage", "target": "others", "amount": 10 }]
+                },
+                {
+                    "id": "mend",
+                    "name": "Mend",
+                    "description": "Heal 5 nearby",
+                    "range": 2,
+                    "actions": [{ "type": "heal", "target": "others", "amount": 5, "area": 1 }]
+                }
+            ],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["smash", "mend"],
+                            "location": [0, 0]
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B1",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["smash"],
+                            "location": [1, 0]
+                        },
+                        {
+                            "name": "Member B2",
+                            "race": "Human",
+                            "base_health": 5,
+                            "max_health": 20,
+                            "cards": ["smash"],
+                            "location": [2, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let member_b1 = CharacterId::new(1);
+        let member_b2 = CharacterId::new(2);
+        let character = battle.characters.get_mut(&member_a).unwrap();
+        character.remaining_actions = 2;
+        character.hand = vec![CardId::new(0), CardId::new(1)];
+
+        assert!(battle.handle_action(&member_a, Action::Act(CardId::new(0), member_b1)));
+        assert!(battle.handle_action(&member_a, Action::Act(CardId::new(1), member_b2)));
+        assert_eq!(battle.characters[&member_b2].health.health, 10);
+        assert!(
+            battle.characters[&member_b1].is_dead(),
+            "Heals skip the dead even though area damage hits them"
+        );
+        assert_eq!(
+            battle.board.grid.get(1, 0),
+            Some(&BoardItem::Remains(member_b1))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_history_hidden_from() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 4, "height": 1 },
+            "walls": [[1, 0]],
+            "cards": [],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [0, 0],
+                            "vision": 3
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [3, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+
+        assert!(battle.handle_action(&CharacterId::new(1), Action::Pass));
+        assert!(battle.handle_action(&CharacterId::new(0), Action::Pass));
+        assert_eq!(
+            battle.history[0].hidden_from,
+            [TeamId::new(0)],
+            "Team A can't see Member B behind the wall"
+        );
+        assert_eq!(battle.history[1].hidden_from, []);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_objectives() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 3, "height": 2 },
+            "cards": [],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "objectives": [{ "type": "reach_tile", "location": [2, 1] }],
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [0, 0]
+                        },
+                        {
+                            "name": "Member A2",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [0, 1]
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "objectives": [
+                        { "type": "protect", "character": "VIP" },
+                        { "type": "survive", "rounds": 3 }
+                    ],
+                    "members": [
+                        {
+                            "name": "VIP",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [2, 0]
+                        },
+                        {
+                            "name": "Guard",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [1, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let vip = CharacterId::new(2);
+        assert_eq!(battle.check_objectives(true), None);
+
+        battle.round = 3;
+        assert_eq!(
+            battle.check_objectives(false),
+            None,
+            "Survival is only checked between rounds"
+        );
+        assert_eq!(
+            battle.check_objectives(true),
+            Some(Victory {
+                team: TeamId::new(1),
+                objective: Objective::Survive(3),
+            })
+        );
+        battle.round = 1;
+
+        battle.characters.get_mut(&vip).unwrap().health = Health::new(0);
+        assert_eq!(
+            battle.check_objectives(false),
+            Some(Victory {
+                team: TeamId::new(0),
+                objective: Objective::EliminateAll,
+            }),
+            "Losing the VIP defeats the whole team"
+        );
+        battle.characters.get_mut(&vip).unwrap().health = Health::new(5);
+
+        battle.board.grid.clear(0, 0);
+        battle.board.grid.set(2, 1, BoardItem::Character(member_a));
+        assert_eq!(
+            battle.check_objectives(false),
+            Some(Victory {
+                team: TeamId::new(0),
+                objective: Objective::ReachTile(GridLocation { x: 2, y: 1 }),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uneven_teams() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 3, "height": 2 },
+            "cards": [{ "id": "wait", "name": "Wait", "description": "", "actions": [] }],
+            "archetypes": [{ "id": "crew", "race": "Human", "base_health": 5, "cards": ["wait"] }],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "id": "scout",
+                            "name": "Scout",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["wait"],
+                            "location": [0, 0]
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "objectives": [{ "type": "protect", "character": "captain" }],
+                    "members": [
+                        { "extends": "crew", "name": "Guard", "location": [1, 0] },
+                        {
+                            "id": "captain",
+                            "name": "Captain",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["wait"],
+                            "location": [2, 1]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+
+        for (x, y, name) in [(0, 0, "Scout"), (1, 0, "Guard"), (2, 1, "Captain")] {
+            let Some(BoardItem::Character(id)) = battle.board.grid.get(x, y) else {
+                panic!("{name} isn't at {x}, {y}");
+            };
+            assert_eq!(battle.characters[id].name, name);
+            assert_eq!(battle.require_actor(id).get_character_id(), id);
+        }
+        let guard = &battle.characters[&CharacterId::new(1)];
+        assert_eq!(guard.health, Health::new(5));
+        assert_eq!(guard.deck, [CardId::new(0)]);
+        assert_eq!(
+            battle.teams[1].objectives,
+            [
+                Objective::EliminateAll,
+                Objective::Protect(CharacterId::new(2))
+            ]
+        );
+
+        Ok(())
+    }
+
+    fn draw_battle_json(rules: &str) -> String {
+        format!(
+            r#"{{
+                "title": "Example Game",
+                "description": "Example Description",
+                "default_hand_size": 1,
+                "board": {{ "width": 2, "height": 1 }},
+                {rules}
+                "cards": [
+                    {{
+                        "id": "explode",
+                        "name": "Explode",
+                        "description": "Deal 10 damage to everyone nearby",
+                        "range": 0,
+                        "actions": [{{ "type": "damage", "target": "self", "amount": 10, "area": 1 }}]
+                    }}
+                ],
+                "teams": [
+                    {{
+                        "name": "Team A",
+                        "members": [
+                            {{
+                                "name": "Member A",
+                                "race": "Machine",
+                                "base_health": 1,
+                                "cards": ["explode"],
+                                "location": [0, 0]
+                            }}
+                        ]
+                    }},
+                    {{
+                        "name": "Team B",
+                        "members": [
+                            {{
+                                "name": "Member B",
+                                "race": "Machine",
+                                "base_health": 1,
+                                "cards": ["explode"],
+                                "location": [1, 0]
+                            }}
+                        ]
+                    }}
+                ]
+            }}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_check_action() -> Result<(), Diagnostics> {
+        let mut battle = Battle::deserialize(
+            &draw_battle_json(""),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let member_b = CharacterId::new(1);
+        let explode = CardId::new(0);
+
+        assert_eq!(
+            battle.check_action(&member_a, &Action::Act(explode, member_a)),
+            Err("Explode isn't in Member A's hand".into())
+        );
+
+        let character = battle.characters.get_mut(&member_a).unwrap();
+        character.hand.push(explode);
+        character.remaining_actions = 1;
+        character.movement = 1;
+        assert_eq!(
+            battle.check_action(&member_a, &Action::Act(explode, member_a)),
+            Ok(())
+        );
+        assert_eq!(
+            battle.check_action(&member_a, &Action::Act(explode, member_b)),
+            Err("Explode can't be used on Member B".into())
+        );
+        assert_eq!(
+            battle.check_action(&member_a, &Action::Act(CardId::new(5), member_a)),
+            Err("Unknown card 5".into())
+        );
+        assert_eq!(
+            battle.check_action(&member_a, &Action::Act(explode, CharacterId::new(7))),
+            Err("Unknown target 7".into())
+        );
+        assert_eq!(
+            battle.check_action(
+                &member_a,
+                &Action::Move(member_b, GridLocation { x: 0, y: 0 })
+            ),
+            Err("Characters can only move themselves".into())
+        );
+        assert_eq!(
+            battle.check_action(
+                &member_a,
+                &Action::Move(member_a, GridLocation { x: 1, y: 0 })
+            ),
+            Err("That tile is blocked".into())
+        );
+        assert_eq!(
+            battle.check_action(
+                &member_a,
+                &Action::Move(member_a, GridLocation { x: 0, y: 1 })
+            ),
+            Err("Characters can only move to an adjacent tile".into())
+        );
+        assert_eq!(battle.check_action(&member_a, &Action::Pass), Ok(()));
+
+        battle
+            .characters
+            .get_mut(&member_a)
+            .unwrap()
+            .remaining_actions = 0;
+        assert_eq!(
+            battle.check_action(&member_a, &Action::Act(explode, member_a)),
+            Err("Member A has no actions left".into())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_legal_actions() -> Result<(), Diagnostics> {
+        let mut battle = Battle::deserialize(
+            &draw_battle_json(""),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let member_b = CharacterId::new(1);
+        let explode = CardId::new(0);
+        assert!(
+            battle.legal_actions(&member_a).is_empty(),
+            "The battle hasn't started"
+        );
+
+        battle.turn_order = vec![member_a, member_b];
+        let character = battle.characters.get_mut(&member_a).unwrap();
+        character.hand = vec![explode, explode];
+        character.remaining_actions = 1;
+        assert_eq!(
+            battle.legal_actions(&member_a),
+            vec![Action::Pass, Action::Act(explode, member_a)]
+        );
+
+        battle.characters.get_mut(&member_a).unwrap().movement = 1;
+        battle.board.grid.clear(1, 0);
+        assert_eq!(
+            battle.legal_actions(&member_a),
+            vec![
+                Action::Pass,
+                Action::Move(member_a, GridLocation { x: 1, y: 0 }),
+                Action::Act(explode, member_a),
+            ]
+        );
+        assert!(battle.legal_actions(&member_b).is_empty(), "Not their turn");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_draws() -> Result<(), Diagnostics> {
+        let mut battle = Battle::deserialize(
+            &draw_battle_json(r#""max_rounds": 3, "stalemate_rounds": 0,"#),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        battle.run_to_completion().await.unwrap();
+        assert_eq!(battle.round, 3);
+        assert_eq!(
+            battle.outcome,
+            Some(BattleOutcome::Draw(DrawReason::RoundLimit(3)))
+        );
+
+        let mut battle = Battle::deserialize(
+            &draw_battle_json(r#""stalemate_rounds": 2,"#),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        battle.run_to_completion().await.unwrap();
+        assert_eq!(battle.round, 2);
+        assert_eq!(
+            battle.outcome,
+            Some(BattleOutcome::Draw(DrawReason::Stalemate(2)))
+        );
+
+        let mut battle = Battle::deserialize(
+            &draw_battle_json(r#""max_rounds": 12,"#),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        battle.run_to_completion().await.unwrap();
+        assert_eq!(
+            battle.outcome,
+            Some(BattleOutcome::Draw(DrawReason::RoundLimit(12))),
+            "Stalemates are only detected when asked for"
+        );
+
+        let walled_off = |area: u64| {
+            draw_battle_json("")
+                .replace(
+                    r#""width": 2, "height": 1 },"#,
+                    r#""width": 3, "height": 1 }, "walls": [[1, 0]],"#,
+                )
+                .replace(r#""area": 1"#, &format!(r#""area": {area}"#))
+                .replace("[1, 0]\n", "[2, 0]\n")
+        };
+        let mut battle = Battle::deserialize(
+            &walled_off(1),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        battle.run_to_completion().await.unwrap();
+        assert_eq!(
+            battle.outcome,
+            Some(BattleOutcome::Draw(DrawReason::Unreachable)),
+            "Battles no one can win end without being told to"
+        );
+        assert_eq!(battle.round, 1);
+
+        let battle = Battle::deserialize(
+            &walled_off(2),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        assert_eq!(
+            battle.check_outcome(true),
+            None,
+            "Cards can reach past walls"
+        );
+
+        let mut battle = Battle::deserialize(
+            &draw_battle_json(""),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let character = battle.characters.get_mut(&member_a).unwrap();
+        character.remaining_actions = 1;
+        character.hand = vec![CardId::new(0)];
+        assert!(battle.handle_action(&member_a, Action::Act(CardId::new(0), member_a)));
+        assert_eq!(
+            battle.check_outcome(false),
+            Some(BattleOutcome::Draw(DrawReason::MutualDefeat))
+        );
+
+        Ok(())
+    }
+
+    async fn turn_order_battle(turn_order: &str) -> Result<Battle, Diagnostics> {
+        let member = |name: &str, speed: u64, x: usize| {
+            format!(
+                r#"{{
+                    "name": "{name}",
+                    "race": "Human",
+                    "base_health": 5,
+                    "cards": [],
+                    "speed": {speed},
+                    "location": [{x}, 0]
+                }}"#
+            )
+        };
+        Battle::deserialize(
+            &format!(
+                r#"{{
+                    "title": "Example Game",
+                    "description": "Example Description",
+                    "default_hand_size": 1,
+                    "board": {{ "width": 4, "height": 1 }},
+                    "turn_order": {turn_order},
+                    "cards": [],
+                    "teams": [
+                        {{ "name": "Team A", "members": [{}, {}] }},
+                        {{ "name": "Team B", "members": [{}, {}] }}
+                    ]
+                }}"#,
+                member("A1", 20, 0),
+                member("A2", 10, 1),
+                member("B1", 5, 2),
+                member("B2", 10, 3),
+            ),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await
+    }
+
+    fn next_turns(battle: &mut Battle) -> Vec<usize> {
+        battle.round += 1;
+        battle
+            .build_turns()
+            .iter()
+            .map(|turn| turn.character.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_turn_order() -> Result<(), Diagnostics> {
+        let mut battle = turn_order_battle(r#"{ "type": "sequential" }"#).await?;
+        assert_eq!(next_turns(&mut battle), vec![0, 1, 2, 3]);
+
+        let mut battle = turn_order_battle(r#"{ "type": "initiative", "roll": [0, 0] }"#).await?;
+        assert_eq!(
+            next_turns(&mut battle),
+            vec![0, 1, 3, 2],
+            "Fastest first, ties keep the listed order"
+        );
+
+        let mut battle = turn_order_battle(r#"{ "type": "team_alternating" }"#).await?;
+        assert_eq!(next_turns(&mut battle), vec![0, 2, 1, 3]);
+        assert_eq!(
+            next_turns(&mut battle),
+            vec![2, 0, 3, 1],
+            "The leading team rotates each round"
+        );
+
+        let mut battle = turn_order_battle(r#"{ "type": "timeline" }"#).await?;
+        assert_eq!(next_turns(&mut battle), vec![0, 0, 1, 3]);
+        assert_eq!(
+            next_turns(&mut battle),
+            vec![0, 0, 1, 2, 3],
+            "Slow characters act every other round"
+        );
+
+        let mut battle = turn_order_battle(r#"{ "type": "timeline" }"#).await?;
+        battle
+            .characters
+            .get_mut(&CharacterId::new(2))
+            .unwrap()
+            .speed = 0;
+        let turns: Vec<usize> = (0..10).flat_map(|_| next_turns(&mut battle)).collect();
+        assert_eq!(
+            turns.iter().filter(|id| **id == 2).count(),
+            1,
+            "A speed of 0 acts as rarely as a speed of 1"
+        );
+
+        Ok(())
+    }
+
+    struct RecordingObserver {
+        updates: Arc<Mutex<Vec<(u16, bool)>>>,
+    }
+
+    #[async_trait]
+    impl BattleObserver for RecordingObserver {
+        async fn on_update(&self, battle: &Battle, _update: &BattleUpdate) {
+            self.updates
+                .lock()
+                .unwrap()
+                .push((battle.round, battle.outcome.is_some()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observers() -> Result<(), Diagnostics> {
+        let mut battle = Battle::deserialize(
+            &draw_battle_json(r#""max_rounds": 2, "stalemate_rounds": 0,"#),
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let updates = Arc::new(Mutex::new(vec![]));
+        battle.observers.push(Box::new(RecordingObserver {
+            updates: updates.clone(),
+        }));
+        battle.run_to_completion().await.unwrap();
+
+        // Both members start their turn and pass in each round, then the battle ends
+        let updates = updates.lock().unwrap();
+        assert_eq!(
+            *updates,
+            vec![
+                (1, false),
+                (1, false),
+                (1, false),
+                (1, false),
+                (2, false),
+                (2, false),
+                (2, false),
+                (2, false),
+                (2, true),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_vision() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 5, "height": 2 },
+            "walls": [[2, 0]],
+            "cards": [
+                {
+                    "id": "arrow",
+                    "name": "Arrow",
+                    "description": "Deal 1 damage",
+                    "range": 5,
+                    "actions": [{ "type": "damage", "target": "others", "amount": 1 }]
+                }
+            ],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["arrow"],
+                            "location": [0, 0],
+                            "vision": 4
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["arrow"],
+                            "location": [4, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let team_a = TeamId::new(0);
+        let team_b = TeamId::new(1);
+        let member_a = CharacterId::new(0);
+        let member_b = CharacterId::new(1);
+        let character = battle.characters.get_mut(&member_a).unwrap();
+        character.remaining_actions = 1;
+        character.hand = vec![CardId::new(0)];
+
+        assert!(battle.is_visible_to(team_a, &member_a));
+        assert!(
+            !battle.is_visible_to(team_a, &member_b),
+            "The wall blocks sight"
+        );
+        assert!(
+            battle.is_visible_to(team_b, &member_a),
+            "No vision range sees everything"
+        );
+        assert!(battle.get_visible_locations(team_b).is_none());
+        assert!(!battle.handle_action(&member_a, Action::Act(CardId::new(0), member_b)));
+
+        battle.board.grid.clear(4, 0);
+        battle.board.grid.set(3, 1, BoardItem::Character(member_b));
+        assert!(battle.is_visible_to(team_a, &member_b));
+        assert!(battle.handle_action(&member_a, Action::Act(CardId::new(0), member_b)));
+
         Ok(())
     }
 }
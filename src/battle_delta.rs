@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use ts_rs::TS;
+
+use crate::{Action, BattleUpdate, CardId, CharacterId, GridLocation, Health};
+
+/// Parts of a serialized [`crate::BattleView`] that have their own deltas. A change to any other
+/// part resets the viewer's whole battle.
+const TRACKED_FIELDS: [&str; 7] = [
+    "characters",
+    "history",
+    "round",
+    "rounds_without_health_change",
+    "board",
+    "turn_order",
+    "outcome",
+];
+
+/// One change to what a viewer can see of a battle. Applying the deltas in order to the last
+/// [`crate::BattleView`] the viewer saw produces the current one. Nested views are sent serialized
+/// exactly as they appear in the full view.
+#[derive(Serialize, Debug, PartialEq, TS)]
+#[serde(tag = "type")]
+pub enum BattleDelta {
+    /// Replaces the whole battle, such as when the viewer has nothing to apply deltas to
+    Reset {
+        #[ts(type = "Battle")]
+        battle: Value,
+    },
+    RoundChanged {
+        round: u16,
+        rounds_without_health_change: u16,
+    },
+    TurnStarted {
+        character_id: CharacterId,
+    },
+    CardPlayed {
+        character_id: CharacterId,
+        card_id: CardId,
+        /// `None` if the target is out of the viewer's sight
+        target_id: Option<CharacterId>,
+    },
+    HealthChanged {
+        character_id: CharacterId,
+        health: Health,
+    },
+    /// A character coming into sight, or any change to one besides its health
+    CharacterChanged {
+        #[ts(type = "Character")]
+        character: Value,
+    },
+    /// A character going out of sight. It is also removed from the board.
+    CharacterHidden {
+        character_id: CharacterId,
+    },
+    /// A change to the board other than characters moving, such as what's in sight
+    BoardChanged {
+        #[ts(type = "Board")]
+        board: Value,
+    },
+    CharacterMoved {
+        character_id: CharacterId,
+        to: GridLocation,
+    },
+    TurnOrderChanged {
+        turn_order: Vec<CharacterId>,
+    },
+    HistoryAppended {
+        #[ts(type = "Array<Array<TemplateEntry<BattleTextEntry>>>")]
+        entries: Vec<Value>,
+    },
+    BattleEnded {
+        #[ts(type = "BattleOutcome | null")]
+        outcome: Value,
+    },
+}
+
+/// Remembers the last view sent to a viewer so only what changed needs to be sent next time
+#[derive(Default)]
+pub struct DeltaTracker {
+    previous: Option<Value>,
+}
+
+impl DeltaTracker {
+    /// Records `view`, a serialized [`crate::BattleView`], as the latest one seen by the viewer and
+    /// returns how it differs from the previous one. The first view is sent in full.
+    pub fn track(&mut self, view: Value, update: &BattleUpdate) -> Vec<BattleDelta> {
+        let deltas = match &self.previous {
+            Some(previous) => diff(previous, &view, update),
+            None => vec![BattleDelta::Reset {
+                battle: view.clone(),
+            }],
+        };
+        self.previous = Some(view);
+        deltas
+    }
+}
+
+fn diff(previous: &Value, next: &Value, update: &BattleUpdate) -> Vec<BattleDelta> {
+    let untracked = |view: &Value| {
+        let mut view = view.as_object().cloned().unwrap_or_default();
+        for field in TRACKED_FIELDS {
+            view.remove(field);
+        }
+        view
+    };
+    if untracked(previous) != untracked(next)
+        || next["history"].as_array().map(Vec::len) < previous["history"].as_array().map(Vec::len)
+    {
+        return vec![BattleDelta::Reset {
+            battle: next.clone(),
+        }];
+    }
+
+    let mut deltas = vec![];
+    if previous["round"] != next["round"]
+        || previous["rounds_without_health_change"] != next["rounds_without_health_change"]
+    {
+        deltas.push(BattleDelta::RoundChanged {
+            round: next["round"].as_u64().unwrap_or_default() as u16,
+            rounds_without_health_change: next["rounds_without_health_change"]
+                .as_u64()
+                .unwrap_or_default() as u16,
+        });
+    }
+
+    let empty = Map::new();
+    let previous_characters = previous["characters"].as_object().unwrap_or(&empty);
+    let next_characters = next["characters"].as_object().unwrap_or(&empty);
+    let is_visible =
+        |character_id: &CharacterId| next_characters.contains_key(&character_id.id.to_string());
+    match update {
+        BattleUpdate::TurnStarted(character_id) if is_visible(character_id) => {
+            deltas.push(BattleDelta::TurnStarted {
+                character_id: *character_id,
+            });
+        }
+        BattleUpdate::ActionTaken(character_id, Action::Act(card_id, target_id))
+            if is_visible(character_id) =>
+        {
+            deltas.push(BattleDelta::CardPlayed {
+                character_id: *character_id,
+                card_id: *card_id,
+                target_id: Some(*target_id).filter(is_visible),
+            });
+        }
+        _ => {}
+    }
+
+    for id in previous_characters.keys() {
+        if !next_characters.contains_key(id) {
+            if let Some(character_id) = CharacterId::parse(id) {
+                deltas.push(BattleDelta::CharacterHidden { character_id });
+            }
+        }
+    }
+    for (id, character) in next_characters {
+        let Some(previous_character) = previous_characters.get(id) else {
+            deltas.push(BattleDelta::CharacterChanged {
+                character: character.clone(),
+            });
+            continue;
+        };
+        if previous_character == character {
+            continue;
+        }
+        let mut previous_without_health = previous_character.clone();
+        previous_without_health["health"] = character["health"].clone();
+        match CharacterId::parse(id) {
+            Some(character_id) if previous_without_health == *character => {
+                deltas.push(BattleDelta::HealthChanged {
+                    character_id,
+                    health: Health::new(character["health"].as_u64().unwrap_or_default()),
+                });
+            }
+            _ => deltas.push(BattleDelta::CharacterChanged {
+                character: character.clone(),
+            }),
+        }
+    }
+
+    let (previous_board, previous_locations) = split_characters(&previous["board"]);
+    let (next_board, next_locations) = split_characters(&next["board"]);
+    if previous_board != next_board {
+        deltas.push(BattleDelta::BoardChanged {
+            board: next["board"].clone(),
+        });
+    }
+    let mut moved: Vec<(&CharacterId, &GridLocation)> = next_locations
+        .iter()
+        .filter(|(character_id, location)| previous_locations.get(character_id) != Some(location))
+        .collect();
+    moved.sort_by_key(|(character_id, _)| **character_id);
+    for (character_id, location) in moved {
+        deltas.push(BattleDelta::CharacterMoved {
+            character_id: *character_id,
+            to: location.clone(),
+        });
+    }
+
+    if previous["turn_order"] != next["turn_order"] {
+        deltas.push(BattleDelta::TurnOrderChanged {
+            turn_order: next["turn_order"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|id| id.as_u64())
+                .map(|id| CharacterId::new(id as usize))
+                .collect(),
+        });
+    }
+
+    let previous_history_length = previous["history"].as_array().map_or(0, Vec::len);
+    if let Some(history) = next["history"].as_array() {
+        if history.len() > previous_history_length {
+            deltas.push(BattleDelta::HistoryAppended {
+                entries: history[previous_history_length..].to_vec(),
+            });
+        }
+    }
+
+    if previous["outcome"] != next["outcome"] {
+        deltas.push(BattleDelta::BattleEnded {
+            outcome: next["outcome"].clone(),
+        });
+    }
+
+    deltas
+}
+
+/// Takes the characters off of a serialized board, returning what's left and where they were
+fn split_characters(board: &Value) -> (Value, HashMap<CharacterId, GridLocation>) {
+    let mut board = board.clone();
+    let mut locations = HashMap::new();
+    if let Some(rows) = board["grid"]["members"].as_array_mut() {
+        for (y, row) in rows.iter_mut().enumerate() {
+            for (x, item) in row.as_array_mut().into_iter().flatten().enumerate() {
+                if let Some(id) = item["Character"].as_u64() {
+                    locations.insert(CharacterId::new(id as usize), GridLocation { x, y });
+                    *item = Value::Null;
+                }
+            }
+        }
+    }
+    (board, locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{BattleDelta, DeltaTracker};
+    use crate::{
+        Action, Battle, BattleFormat, BattleUpdate, BattleView, CardId, CharacterId,
+        DefaultRandomProvider, Diagnostics, GridLocation, Health, HistoryEntry, TeamId,
+    };
+
+    fn move_item(battle: &mut Battle, from: (usize, usize), to: (usize, usize)) {
+        let item = battle.board.grid.clear(from.0, from.1).unwrap();
+        battle.board.grid.set(to.0, to.1, item);
+    }
+
+    fn view(battle: &Battle, viewer: Option<TeamId>) -> serde_json::Value {
+        serde_json::to_value(BattleView::new(battle, viewer)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_deltas() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 5, "height": 1 },
+            "walls": [[3, 0]],
+            "cards": [
+                {
+                    "id": "rest",
+                    "name": "Rest",
+                    "description": "Do nothing",
+                    "actions": []
+                }
+            ],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["rest"],
+                            "location": [0, 0],
+                            "vision": 4
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["rest"],
+                            "location": [2, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        let member_a = CharacterId::new(0);
+        let member_b = CharacterId::new(1);
+        let team_a = Some(TeamId::new(0));
+        let mut tracker = DeltaTracker::default();
+
+        let first_view = view(&battle, team_a);
+        assert_eq!(
+            tracker.track(first_view.clone(), &BattleUpdate::GameOver),
+            vec![BattleDelta::Reset { battle: first_view }],
+            "Starts with the full battle"
+        );
+
+        let update = BattleUpdate::ActionTaken(member_b, Action::Act(CardId::new(0), member_a));
+        battle.characters.get_mut(&member_a).unwrap().health = Health::new(3);
+        battle.history.push(HistoryEntry::public(vec![]));
+        assert_eq!(
+            tracker.track(view(&battle, team_a), &update),
+            vec![
+                BattleDelta::CardPlayed {
+                    character_id: member_b,
+                    card_id: CardId::new(0),
+                    target_id: Some(member_a),
+                },
+                BattleDelta::HealthChanged {
+                    character_id: member_a,
+                    health: Health::new(3),
+                },
+                BattleDelta::HistoryAppended {
+                    entries: vec![json!([])],
+                },
+            ]
+        );
+
+        move_item(&mut battle, (0, 0), (1, 0));
+        assert_eq!(
+            tracker.track(view(&battle, team_a), &BattleUpdate::GameOver),
+            vec![BattleDelta::CharacterMoved {
+                character_id: member_a,
+                to: GridLocation { x: 1, y: 0 },
+            }]
+        );
+
+        move_item(&mut battle, (2, 0), (4, 0));
+        assert_eq!(
+            tracker.track(view(&battle, team_a), &BattleUpdate::GameOver),
+            vec![BattleDelta::CharacterHidden {
+                character_id: member_b
+            }],
+            "Member B moved behind the wall"
+        );
+
+        assert_eq!(
+            tracker.track(view(&battle, team_a), &BattleUpdate::GameOver),
+            vec![],
+            "Nothing changed"
+        );
+
+        Ok(())
+    }
+}
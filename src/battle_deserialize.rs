@@ -1,12 +1,18 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use crate::{
-    battle_file, web_actor::WebActor, Actor, Battle, Board, BoardItem, Card, CardAction, CardId,
-    Character, CharacterId, CharacterRace, DumbActor, Health, RandomProvider, Target, Team, TeamId,
-    TerminalActor, U64Range,
+    battle_file::{self, Identifier},
+    web_actor::{WebActor, WebActorServer},
+    Actor, Battle, BattleFileError, BattleFormat, Board, BoardItem, Card, CardAction, CardId,
+    CardPickup, Character, CharacterId, CharacterRace, DeadBody, DeathRule, Diagnostics, DumbActor,
+    GridLocation, Health, JsonPath, Objective, RandomProvider, Target, Team, TeamId, TerminalActor,
+    TurnOrder, U64Range,
 };
 use futures::future::join_all;
 
+const DEFAULT_SPEED: u64 = 10;
+const DEFAULT_INITIATIVE_ROLL: U64Range = U64Range(1, 20);
+
 fn normalize_maybe_u64_range(life_number_range: &battle_file::MaybeU64Range) -> U64Range {
     match *life_number_range {
         battle_file::MaybeU64Range::Absolute(value) => U64Range(value, value),
@@ -14,61 +20,209 @@ fn normalize_maybe_u64_range(life_number_range: &battle_file::MaybeU64Range) ->
     }
 }
 
+/// The ids the engine uses for the cards and team members a battle file refers to by identifier.
+/// Cards are numbered in the order `all_cards` lists them, and characters in order of team and then
+/// member.
+struct EngineIds {
+    cards: HashMap<Identifier, CardId>,
+    characters: HashMap<Identifier, CharacterId>,
+}
+
+impl EngineIds {
+    /// The battle must have been validated, so every identifier is unique
+    fn new(battle: &battle_file::Battle) -> Self {
+        Self {
+            cards: battle
+                .all_cards()
+                .enumerate()
+                .map(|(index, card)| (card.id.clone(), CardId::new(index)))
+                .collect(),
+            characters: battle
+                .teams
+                .iter()
+                .flat_map(|team| &team.members)
+                .enumerate()
+                .map(|(index, member)| (member.id().into(), CharacterId::new(index)))
+                .collect(),
+        }
+    }
+
+    fn card(&self, id: &str) -> CardId {
+        self.cards[id]
+    }
+
+    fn character(&self, id: &str) -> CharacterId {
+        self.characters[id]
+    }
+}
+
 impl Battle {
+    /// Loads a battle without any web players. Fails if the battle requires any.
     pub async fn deserialize(
         data: &str,
+        format: BattleFormat,
+        asset_directory: Option<PathBuf>,
+        random_provider: Box<dyn RandomProvider>,
+    ) -> Result<Self, Diagnostics> {
+        Self::deserialize_with_server(data, format, asset_directory, random_provider, None).await
+    }
+
+    /// Loads a battle whose human players connect through `server`, hosted under a session named
+    /// after `session_id`
+    pub async fn deserialize_in_session(
+        data: &str,
+        format: BattleFormat,
+        asset_directory: Option<PathBuf>,
+        random_provider: Box<dyn RandomProvider>,
+        server: &Arc<WebActorServer>,
+        session_id: &str,
+    ) -> Result<Self, Diagnostics> {
+        Self::deserialize_with_server(
+            data,
+            format,
+            asset_directory,
+            random_provider,
+            Some((server, session_id)),
+        )
+        .await
+    }
+
+    async fn deserialize_with_server(
+        data: &str,
+        format: BattleFormat,
         asset_directory: Option<PathBuf>,
         random_provider: Box<dyn RandomProvider>,
-    ) -> Result<Self, String> {
-        let battle = battle_file::Battle::parse_from_str(data)?;
+        server: Option<(&Arc<WebActorServer>, &str)>,
+    ) -> Result<Self, Diagnostics> {
+        let mut battle =
+            battle_file::Battle::parse_from_str(data, format, asset_directory.as_deref())?;
+        // Validation made sure every member has the fields it needs once merged
+        battle.merge_archetypes();
 
         let mut board = Board::new(battle.board.width, battle.board.height);
 
-        let max_team_size = battle
-            .teams
-            .iter()
-            .map(|team| team.members.len())
-            .max()
-            .unwrap_or(0);
-        {
-            for (team_index, team) in battle.teams.iter().enumerate() {
-                for (index, member) in team.members.iter().enumerate() {
-                    let (x, y) = member.location;
-                    if !board.grid.is_valid(x, y) {
-                        return Err(format!("Invalid team member position: {x}, {y}"));
+        let ids = EngineIds::new(&battle);
+
+        // Positions were checked when parsing so nothing is off the board or overlapping
+        for member in battle.teams.iter().flat_map(|team| &team.members) {
+            let (x, y) = member.location;
+            board
+                .grid
+                .set(x, y, BoardItem::Character(ids.character(member.id())));
+        }
+        for pickup in &battle.pickups {
+            let (x, y) = pickup.location;
+            board
+                .grid
+                .set(x, y, BoardItem::Card(ids.card(&pickup.card)));
+        }
+        for (x, y) in battle.walls.iter().copied() {
+            board.grid.set(x, y, BoardItem::Wall);
+        }
+
+        let mut teams = vec![];
+        for (index, team) in battle.teams.iter().enumerate() {
+            let mut objectives = vec![Objective::EliminateAll];
+            for objective in &team.objectives {
+                objectives.push(match objective {
+                    battle_file::Objective::EliminateAll => continue,
+                    battle_file::Objective::Defeat { character } => {
+                        Objective::Defeat(ids.character(character))
+                    }
+                    battle_file::Objective::Survive { rounds } => Objective::Survive(*rounds),
+                    battle_file::Objective::ReachTile { location: (x, y) } => {
+                        Objective::ReachTile(GridLocation { x: *x, y: *y })
                     }
-                    // Makes strong assumptions about the way character ids are picked, incrementing in the same order of team and member
-                    if let Some(_prev_id) = board.grid.set(
-                        x,
-                        y,
-                        BoardItem::Character(CharacterId::new(team_index * max_team_size + index)),
-                    ) {
-                        return Err(format!("Multiple entries found at {x}, {y}"));
+                    battle_file::Objective::Protect { character } => {
+                        Objective::Protect(ids.character(character))
                     }
-                }
+                });
             }
+            teams.push(Team {
+                id: TeamId::new(index.try_into().unwrap()),
+                name: team.name.clone(),
+                objectives,
+            });
         }
 
         let canonical_asset_directory =
             asset_directory.map(|path_buf| path_buf.canonicalize().unwrap());
-        let asset_directory = canonical_asset_directory.as_deref();
-        Ok(Battle {
+
+        // Where the first human player is in the file, if there is one
+        let first_web_player = battle
+            .teams
+            .iter()
+            .enumerate()
+            .find_map(|(team_index, team)| {
+                let member_index = team.members.iter().position(|member| member.is_player)?;
+                Some(
+                    JsonPath::default()
+                        .key("teams")
+                        .index(team_index)
+                        .key("members")
+                        .index(member_index)
+                        .key("is_player"),
+                )
+            })
+            .filter(|_| !cfg!(feature = "terminal_ui"));
+        let web_session = match (server, first_web_player) {
+            (Some((server, session_id)), _) => Some(
+                server
+                    .open_session(session_id, &battle.title, &battle.description)
+                    .await,
+            ),
+            (None, Some(path)) => {
+                return Err(Diagnostics::locate(
+                    data,
+                    format,
+                    vec![(BattleFileError::ServerRequired, path)],
+                ))
+            }
+            (None, None) => None,
+        };
+        let observers = match &web_session {
+            Some(web_session) => vec![web_session.observer()],
+            None => vec![],
+        };
+        let web_session = &web_session;
+        let engine_battle = Battle {
             history: vec![],
-            introduction: battle.introduction,
             random_provider,
             default_turn_actions: 1,
+            card_pickup: match battle.card_pickup {
+                Some(battle_file::CardPickup::Hand) => CardPickup::Hand,
+                Some(battle_file::CardPickup::Deck) | None => CardPickup::Deck,
+            },
+            death_rule: battle
+                .dead_characters
+                .as_ref()
+                .map(|rule| {
+                    let default = DeathRule::default();
+                    DeathRule {
+                        body: match rule.body {
+                            battle_file::DeadBody::Remove => DeadBody::Remove,
+                            battle_file::DeadBody::Corpse => DeadBody::Corpse,
+                            battle_file::DeadBody::Remains => DeadBody::Remains,
+                        },
+                        area_effects_hit_dead: rule
+                            .area_effects_hit_dead
+                            .unwrap_or(default.area_effects_hit_dead),
+                    }
+                })
+                .unwrap_or_default(),
             characters: battle
                 .teams
                 .iter()
                 .flat_map(|team| &team.members)
-                .enumerate()
-                .map(|(index, member)| {
+                .map(|member| {
+                    let id = ids.character(member.id());
+                    let base_health = member.base_health.unwrap();
                     (
-                        CharacterId::new(index),
+                        id,
                         Character {
-                            id: CharacterId::new(index),
+                            id,
                             name: member.name.clone(),
-                            race: match member.race {
+                            race: match member.race.as_ref().unwrap() {
                                 battle_file::Race::Human => CharacterRace::Human,
                                 battle_file::Race::Machine => CharacterRace::Machine,
                             },
@@ -78,34 +232,42 @@ impl Battle {
                             deck: member
                                 .cards
                                 .iter()
-                                .map(|card_id| CardId::new(*card_id))
+                                .flatten()
+                                .map(|card_id| ids.card(card_id))
                                 .collect(),
-                            health: Health::new(member.base_health),
-                            max_health: Health::new(
-                                member.max_health.unwrap_or(member.base_health),
-                            ),
+                            health: Health::new(base_health),
+                            max_health: Health::new(member.max_health.unwrap_or(base_health)),
                             hand_size: member.hand_size.unwrap_or(battle.default_hand_size),
                             movement: 0,
                             default_movement: member
                                 .movement
                                 .unwrap_or(battle.default_movement.unwrap_or(0)),
+                            loot: member
+                                .loot
+                                .iter()
+                                .flatten()
+                                .map(|card_id| ids.card(card_id))
+                                .collect(),
+                            speed: member.speed.unwrap_or(DEFAULT_SPEED),
+                            vision: member.vision,
+                            action_points: 0,
                         },
                     )
                 })
                 .collect(),
             cards: battle
-                .cards
-                .iter()
+                .all_cards()
                 .map(|card| {
                     let map_target = |target: &battle_file::Target| match target {
                         battle_file::Target::Me => Target::Me,
                         battle_file::Target::Others => Target::Others,
                         battle_file::Target::Any => Target::Any,
                     };
+                    let id = ids.card(&card.id);
                     (
-                        CardId::new(card.id),
+                        id,
                         Card {
-                            id: CardId::new(card.id),
+                            id,
                             name: card.name.clone(),
                             description: card.description.clone(),
                             flavor: card.flavor.clone(),
@@ -156,54 +318,67 @@ impl Battle {
                     )
                 })
                 .collect(),
-            teams: battle
-                .teams
-                .iter()
-                .enumerate()
-                .map(|(index, team)| Team {
-                    id: TeamId::new(index.try_into().unwrap()),
-                    name: team.name.clone(),
-                })
-                .collect(),
+            teams,
             actors: join_all(
                 battle
                     .teams
                     .iter()
                     .enumerate()
                     .flat_map(|(team_index, team)| {
-                        team.members
-                            .iter()
-                            .enumerate()
-                            .map(move |(member_index, team_member)| {
-                                let character_id =
-                                    CharacterId::new(team_index * max_team_size + member_index);
-                                async move {
-                                    (
-                                        TeamId::new(team_index.try_into().unwrap()),
-                                        if team_member.is_player {
-                                            if cfg!(feature = "terminal_ui") {
-                                                Box::new(TerminalActor { character_id })
-                                                    as Box<dyn Actor>
-                                            } else {
-                                                Box::new(
-                                                    WebActor::new(character_id, asset_directory)
-                                                        .await
-                                                        .unwrap(),
-                                                )
-                                                    as Box<dyn Actor>
-                                            }
+                        let ids = &ids;
+                        team.members.iter().map(move |team_member| {
+                            let character_id = ids.character(team_member.id());
+                            async move {
+                                (
+                                    TeamId::new(team_index.try_into().unwrap()),
+                                    if team_member.is_player {
+                                        if cfg!(feature = "terminal_ui") {
+                                            Box::new(TerminalActor { character_id })
+                                                as Box<dyn Actor>
                                         } else {
-                                            Box::new(DumbActor { character_id }) as Box<dyn Actor>
-                                        },
-                                    )
-                                }
-                            })
+                                            Box::new(
+                                                WebActor::new(
+                                                    character_id,
+                                                    web_session.clone().unwrap(),
+                                                )
+                                                .await,
+                                            )
+                                                as Box<dyn Actor>
+                                        }
+                                    } else {
+                                        Box::new(DumbActor { character_id }) as Box<dyn Actor>
+                                    },
+                                )
+                            }
+                        })
                     }),
             )
             .await,
             round: 0,
             board,
             asset_directory: canonical_asset_directory,
-        })
+            max_rounds: battle.max_rounds,
+            stalemate_rounds: battle.stalemate_rounds.unwrap_or_default(),
+            rounds_without_health_change: 0,
+            outcome: None,
+            turn_order_rule: match &battle.turn_order {
+                None | Some(battle_file::TurnOrder::Sequential) => TurnOrder::Sequential,
+                Some(battle_file::TurnOrder::Initiative { roll }) => TurnOrder::Initiative(
+                    roll.as_ref()
+                        .map(normalize_maybe_u64_range)
+                        .unwrap_or(DEFAULT_INITIATIVE_ROLL),
+                ),
+                Some(battle_file::TurnOrder::TeamAlternating) => TurnOrder::TeamAlternating,
+                Some(battle_file::TurnOrder::Timeline) => TurnOrder::Timeline,
+            },
+            turn_order: vec![],
+            observers,
+            // Moved last since everything else borrows from the battle file
+            introduction: battle.introduction,
+        };
+        if let Some(web_session) = web_session {
+            web_session.show(&engine_battle).await;
+        }
+        Ok(engine_battle)
     }
 }
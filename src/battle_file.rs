@@ -1,63 +1,489 @@
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject},
+    schema_for, JsonSchema,
+};
 use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs, io, path::Path};
+use ts_rs::TS;
+
+use crate::{
+    battle_imports::ImportLoader, battle_migrations, BattleFileError, BattleFormat, Diagnostic,
+    Diagnostics, JsonPath,
+};
 
 pub type LifeNumber = u64;
-pub type CardId = usize;
+/// How a battle file refers to its cards and team members, such as "punch" or "captain"
+pub type Identifier = String;
 pub type HandSize = u8;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Battle {
+    /// The version of the format the file is written in. Files without one are from before the
+    /// format was versioned, which is version 1.
+    pub format_version: Option<u64>,
     pub title: String,
     pub description: String,
     pub board: Board,
     pub introduction: Option<StoryCard>,
     pub default_hand_size: HandSize,
     pub default_movement: Option<u64>,
+    /// Libraries of cards and archetypes shared with other battles
+    #[serde(default)]
+    pub imports: Vec<Import>,
+    /// Overrides any imported card with the same id
     pub cards: Vec<Card>,
+    /// Overrides any imported archetype with the same id
+    #[serde(default)]
+    pub archetypes: Vec<Archetype>,
     pub teams: Vec<Team>,
+    #[serde(default)]
+    pub pickups: Vec<Pickup>,
+    /// Impassable tiles that also block line of sight
+    #[serde(default)]
+    pub walls: Vec<(usize, usize)>,
+    pub card_pickup: Option<CardPickup>,
+    pub dead_characters: Option<DeathRule>,
+    /// The battle ends in a draw once this many rounds have been played
+    pub max_rounds: Option<u16>,
+    /// The battle ends in a draw after this many rounds without any health changing. Stalemates
+    /// aren't detected if this is left out or 0.
+    pub stalemate_rounds: Option<u16>,
+    pub turn_order: Option<TurnOrder>,
+    /// Everything brought in by `imports`, once they are resolved
+    #[serde(skip)]
+    pub imported: Library,
 }
 
 impl Battle {
-    pub fn parse_from_str(data: &str) -> Result<Self, String> {
-        let battle: Battle = serde_json::from_str::<Battle>(data).map_err(|err| err.to_string())?;
+    /// The JSON Schema battle files are validated against, for editors to offer completion and
+    /// catch mistakes before the battle is loaded
+    pub fn json_schema() -> String {
+        serde_json::to_string_pretty(&schema_for!(Battle)).unwrap()
+    }
+
+    /// Loads a battle file, failing if it has any errors. Images are checked against
+    /// `asset_directory` if there is one.
+    pub fn parse_from_str(
+        data: &str,
+        format: BattleFormat,
+        asset_directory: Option<&Path>,
+    ) -> Result<Self, Diagnostics> {
+        match Self::parse_with_diagnostics(data, format, asset_directory) {
+            (Some(battle), diagnostics) if !diagnostics.has_errors() => Ok(battle),
+            (_, diagnostics) => Err(diagnostics),
+        }
+    }
+
+    /// Finds every problem with a battle file, including warnings that don't keep it from loading
+    pub fn check(data: &str, format: BattleFormat, asset_directory: Option<&Path>) -> Diagnostics {
+        Self::parse_with_diagnostics(data, format, asset_directory).1
+    }
 
-        for (index, card) in battle.cards.iter().enumerate() {
-            if card.id != index {
-                return Err(format!("Card with id {} should be {}", card.id, index));
+    fn parse_with_diagnostics(
+        data: &str,
+        format: BattleFormat,
+        asset_directory: Option<&Path>,
+    ) -> (Option<Self>, Diagnostics) {
+        Self::parse_with_loader(
+            data,
+            format,
+            asset_directory,
+            &|path| match asset_directory {
+                Some(asset_directory) => fs::read_to_string(asset_directory.join(path)),
+                None => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "imports are relative to the asset directory, which there isn't one of",
+                )),
+            },
+        )
+    }
+
+    /// Parses a battle file, resolving its imports with `load` before validating it
+    pub(crate) fn parse_with_loader(
+        data: &str,
+        format: BattleFormat,
+        asset_directory: Option<&Path>,
+        load: &ImportLoader,
+    ) -> (Option<Self>, Diagnostics) {
+        match Self::parse_any_version(data, format) {
+            Ok(mut battle) => {
+                let mut problems = battle.resolve_imports(load);
+                problems.extend(battle.validate(asset_directory));
+                (Some(battle), Diagnostics::locate(data, format, problems))
             }
+            Err(diagnostics) => (None, diagnostics),
+        }
+    }
+
+    /// Parses a battle file written in any version of the format, upgrading it to the current one
+    fn parse_any_version(data: &str, format: BattleFormat) -> Result<Self, Diagnostics> {
+        let syntax_error = |diagnostic| Diagnostics(vec![diagnostic]);
+        let original = format.parse(data).map_err(syntax_error)?;
+        let mut migrated = original.clone();
+        battle_migrations::migrate(&mut migrated).map_err(|error| {
+            Diagnostics::locate(
+                data,
+                format,
+                vec![(error, JsonPath::default().key("format_version"))],
+            )
+        })?;
+        if migrated == original {
+            // Keeps the location of any errors
+            format.deserialize(data).map_err(syntax_error)
+        } else {
+            serde_json::from_value(migrated).map_err(|err| syntax_error(Diagnostic::syntax(&err)))
+        }
+    }
+
+    /// Finds the problems that the format of the file alone can't rule out, along with where each
+    /// one is
+    fn validate(&self, asset_directory: Option<&Path>) -> Vec<(BattleFileError, JsonPath)> {
+        let root = JsonPath::default();
+        let mut problems = vec![];
+        let card_exists = |card_id: &str| self.all_cards().any(|card| card.id == card_id);
+        let is_on_board = |x: usize, y: usize| x < self.board.width && y < self.board.height;
+
+        validate_cards(&self.cards, &root.key("cards"), &mut problems);
+        validate_archetypes(
+            &self.archetypes,
+            &root.key("archetypes"),
+            &card_exists,
+            &|id| self.archetype(id),
+            &mut problems,
+        );
 
-            for action in &card.actions {
-                let target = match action {
-                    CardAction::Damage { target, .. } => target,
-                    CardAction::Heal { target, .. } => target,
-                    CardAction::GainAction { target, .. } => target,
-                    CardAction::Move { target, .. } => target,
+        let mut member_ids = HashSet::new();
+        // Everything on the board, in the order it is placed
+        let mut placements = vec![];
+        for (team_index, team) in self.teams.iter().enumerate() {
+            let team_path = root.key("teams").index(team_index);
+            if team.members.is_empty() {
+                problems.push((
+                    BattleFileError::EmptyTeam {
+                        team: team.name.clone(),
+                    },
+                    team_path.key("members"),
+                ));
+            }
+
+            for (member_index, member) in team.members.iter().enumerate() {
+                let path = team_path.key("members").index(member_index);
+                if !member_ids.insert(member.id()) {
+                    // Members without an id are identified by their name instead
+                    let key = if member.id.is_some() { "id" } else { "name" };
+                    problems.push((
+                        BattleFileError::DuplicateId(member.id().into()),
+                        path.key(key),
+                    ));
+                }
+                for (key, cards) in [("cards", &member.cards), ("loot", &member.loot)] {
+                    for (index, card_id) in cards.iter().flatten().enumerate() {
+                        if !card_exists(card_id) {
+                            problems.push((
+                                BattleFileError::UnknownCard(card_id.clone()),
+                                path.key(key).index(index),
+                            ));
+                        }
+                    }
+                }
+                placements.push((member.location, path.key("location")));
+
+                let merged = match self.with_archetypes(member) {
+                    Ok(merged) => merged,
+                    Err(error) => {
+                        problems.push((error, path.key("extends")));
+                        continue;
+                    }
                 };
-                if target != &Target::Me && card.range.is_none() {
-                    return Err(format!("Card with id {} has an action that can target others but without a range specified", card.id));
+                // Inherited fields are blamed on the archetype the member extends
+                let own_or_inherited = |is_own: bool, key| match is_own {
+                    true => path.key(key),
+                    false => path.key("extends"),
+                };
+                for (field, is_missing) in [
+                    ("race", merged.race.is_none()),
+                    ("base_health", merged.base_health.is_none()),
+                    ("cards", merged.cards.is_none()),
+                ] {
+                    if is_missing {
+                        problems.push((
+                            BattleFileError::MissingField {
+                                name: member.name.clone(),
+                                field,
+                            },
+                            path.clone(),
+                        ));
+                    }
+                }
+                if let (Some(max_health), Some(base_health)) =
+                    (merged.max_health, merged.base_health)
+                {
+                    if max_health < base_health {
+                        problems.push((
+                            BattleFileError::MaxHealthBelowBase {
+                                name: member.name.clone(),
+                                base_health,
+                                max_health,
+                            },
+                            own_or_inherited(member.max_health.is_some(), "max_health"),
+                        ));
+                    }
+                }
+                if let (Some(image), Some(asset_directory)) = (&merged.image, asset_directory) {
+                    if !asset_directory.join(image).is_file() {
+                        problems.push((
+                            BattleFileError::MissingImage {
+                                image: image.clone(),
+                            },
+                            own_or_inherited(member.image.is_some(), "image"),
+                        ));
+                    }
                 }
             }
-        }
 
-        let mut player_found = false;
-        for team in &battle.teams {
-            for team_member in &team.members {
-                if team_member.is_player {
-                    if player_found {
-                        return Err("Multiple playable team members found.")?;
+            for (index, objective) in team.objectives.iter().enumerate() {
+                let path = team_path.key("objectives").index(index);
+                match objective {
+                    Objective::Defeat { character } | Objective::Protect { character }
+                        if !self
+                            .teams
+                            .iter()
+                            .flat_map(|team| &team.members)
+                            .any(|member| member.id() == character) =>
+                    {
+                        problems.push((
+                            BattleFileError::UnknownCharacter(character.clone()),
+                            path.key("character"),
+                        ));
                     }
-                    player_found = true;
+                    Objective::ReachTile { location: (x, y) } if !is_on_board(*x, *y) => {
+                        problems.push((
+                            BattleFileError::OutOfBounds { x: *x, y: *y },
+                            path.key("location"),
+                        ));
+                    }
+                    _ => {}
                 }
             }
         }
 
-        Ok(battle)
+        for (index, pickup) in self.pickups.iter().enumerate() {
+            let path = root.key("pickups").index(index);
+            if !card_exists(&pickup.card) {
+                problems.push((
+                    BattleFileError::UnknownCard(pickup.card.clone()),
+                    path.key("card"),
+                ));
+            }
+            placements.push((pickup.location, path.key("location")));
+        }
+        for (index, wall) in self.walls.iter().enumerate() {
+            placements.push((*wall, root.key("walls").index(index)));
+        }
+
+        let mut occupied = HashSet::new();
+        for ((x, y), path) in placements {
+            if !is_on_board(x, y) {
+                problems.push((BattleFileError::OutOfBounds { x, y }, path));
+            } else if !occupied.insert((x, y)) {
+                problems.push((BattleFileError::Overlap { x, y }, path));
+            }
+        }
+
+        let used_cards: HashSet<&Identifier> = self
+            .teams
+            .iter()
+            .flat_map(|team| &team.members)
+            .flat_map(|member| member.cards.iter().chain(&member.loot).flatten())
+            .chain(
+                self.archetypes
+                    .iter()
+                    .flat_map(|archetype| archetype.cards.iter().chain(&archetype.loot).flatten()),
+            )
+            .chain(self.pickups.iter().map(|pickup| &pickup.card))
+            .collect();
+        for (index, card) in self.cards.iter().enumerate() {
+            if !used_cards.contains(&card.id) {
+                problems.push((
+                    BattleFileError::UnusedCard(card.id.clone()),
+                    root.key("cards").index(index),
+                ));
+            }
+        }
+
+        problems
+    }
+
+    /// Finds an archetype by id, preferring the battle's own over imported ones
+    pub fn archetype(&self, id: &str) -> Option<&Archetype> {
+        self.archetypes
+            .iter()
+            .chain(&self.imported.archetypes)
+            .find(|archetype| archetype.id == id)
+    }
+
+    /// The member with every field it leaves out taken from the archetypes it extends
+    pub fn with_archetypes(&self, member: &TeamMember) -> Result<TeamMember, BattleFileError> {
+        let mut merged = member.clone();
+        for archetype in archetype_chain(member.extends.as_deref(), &|id| self.archetype(id))? {
+            merged.inherit(archetype);
+        }
+        Ok(merged)
+    }
+
+    /// Replaces every member with its merged form. The battle must have been validated.
+    pub fn merge_archetypes(&mut self) {
+        let merged: Vec<Vec<TeamMember>> = self
+            .teams
+            .iter()
+            .map(|team| {
+                team.members
+                    .iter()
+                    .map(|member| self.with_archetypes(member).unwrap())
+                    .collect()
+            })
+            .collect();
+        for (team, members) in self.teams.iter_mut().zip(merged) {
+            team.members = members;
+        }
+    }
+}
+
+/// The archetypes something that extends `extends` takes its fields from, nearest first
+fn archetype_chain<'a>(
+    mut extends: Option<&'a str>,
+    find_archetype: &dyn Fn(&str) -> Option<&'a Archetype>,
+) -> Result<Vec<&'a Archetype>, BattleFileError> {
+    let mut chain: Vec<&Archetype> = vec![];
+    while let Some(id) = extends {
+        if let Some(start) = chain.iter().position(|archetype| archetype.id == id) {
+            let cycle = chain[start..]
+                .iter()
+                .map(|archetype| archetype.id.clone())
+                .chain([id.into()])
+                .collect();
+            return Err(BattleFileError::ArchetypeCycle(cycle));
+        }
+        let archetype =
+            find_archetype(id).ok_or_else(|| BattleFileError::UnknownArchetype(id.into()))?;
+        chain.push(archetype);
+        extends = archetype.extends.as_deref();
+    }
+    Ok(chain)
+}
+
+/// Checks the cards defined in a single file, whether a battle or a library
+pub(crate) fn validate_cards(
+    cards: &[Card],
+    path: &JsonPath,
+    problems: &mut Vec<(BattleFileError, JsonPath)>,
+) {
+    let mut card_ids = HashSet::new();
+    for (index, card) in cards.iter().enumerate() {
+        let path = path.index(index);
+        if !card_ids.insert(&card.id) {
+            problems.push((
+                BattleFileError::DuplicateId(card.id.clone()),
+                path.key("id"),
+            ));
+        }
+        let targets_others = card
+            .actions
+            .iter()
+            .any(|action| action.target() != &Target::Me);
+        if targets_others && card.range.is_none() {
+            problems.push((
+                BattleFileError::MissingRange {
+                    card_id: card.id.clone(),
+                },
+                path,
+            ));
+        }
     }
 }
 
+/// Checks the archetypes defined in a single file, given how to find what they refer to
+pub(crate) fn validate_archetypes<'a>(
+    archetypes: &'a [Archetype],
+    path: &JsonPath,
+    card_exists: &dyn Fn(&str) -> bool,
+    find_archetype: &dyn Fn(&str) -> Option<&'a Archetype>,
+    problems: &mut Vec<(BattleFileError, JsonPath)>,
+) {
+    let mut archetype_ids = HashSet::new();
+    for (index, archetype) in archetypes.iter().enumerate() {
+        let path = path.index(index);
+        if !archetype_ids.insert(&archetype.id) {
+            problems.push((
+                BattleFileError::DuplicateId(archetype.id.clone()),
+                path.key("id"),
+            ));
+        }
+        if let Err(error) = archetype_chain(archetype.extends.as_deref(), find_archetype) {
+            problems.push((error, path.key("extends")));
+        }
+        for (key, cards) in [("cards", &archetype.cards), ("loot", &archetype.loot)] {
+            for (index, card_id) in cards.iter().flatten().enumerate() {
+                if !card_exists(card_id) {
+                    problems.push((
+                        BattleFileError::UnknownCard(card_id.clone()),
+                        path.key(key).index(index),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// A file of cards and archetypes shared between battles
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Library {
+    #[serde(default)]
+    pub imports: Vec<Import>,
+    /// Overrides any imported card with the same id
+    #[serde(default)]
+    pub cards: Vec<Card>,
+    /// Overrides any imported archetype with the same id
+    #[serde(default)]
+    pub archetypes: Vec<Archetype>,
+}
+
+/// Brings in the cards and archetypes of a library, with their ids prefixed by the namespace and a
+/// dot, such as `basics.punch`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Import {
+    /// Relative to the asset directory
+    pub path: String,
+    /// Defaults to the file name without its extension
+    pub namespace: Option<Identifier>,
+}
+
+/// Defaults shared by many team members, such as every pirate in a crew
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Archetype {
+    pub id: Identifier,
+    /// Takes every field left out from this archetype
+    pub extends: Option<Identifier>,
+    pub race: Option<Race>,
+    pub base_health: Option<LifeNumber>,
+    pub max_health: Option<LifeNumber>,
+    pub cards: Option<Vec<Identifier>>,
+    pub hand_size: Option<HandSize>,
+    pub image: Option<String>,
+    pub movement: Option<u64>,
+    pub speed: Option<u64>,
+    pub vision: Option<u64>,
+    pub loot: Option<Vec<Identifier>>,
+}
+
 pub type StoryCard = Vec<StoryCardEntry>;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, TS)]
 #[serde(rename_all = "snake_case")]
 #[serde(deny_unknown_fields)]
 pub enum StoryCardEntry {
@@ -65,37 +491,151 @@ pub enum StoryCardEntry {
     P(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[serde(deny_unknown_fields)]
+pub enum TurnOrder {
+    Sequential,
+    Initiative {
+        /// Added to each member's speed every round. Defaults to 1 to 20.
+        roll: Option<MaybeU64Range>,
+    },
+    TeamAlternating,
+    Timeline,
+}
+
+/// How defeated characters are treated for the rest of the battle
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DeathRule {
+    pub body: DeadBody,
+    pub area_effects_hit_dead: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[serde(deny_unknown_fields)]
+pub enum DeadBody {
+    Remove,
+    Corpse,
+    Remains,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Board {
     pub width: usize,
     pub height: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Team {
     pub name: String,
     pub members: Vec<TeamMember>,
+    /// Additional ways for the team to win. Eliminating every opposing team always wins.
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[serde(deny_unknown_fields)]
+pub enum Objective {
+    EliminateAll,
+    /// Defeat the team member with this id
+    Defeat {
+        character: Identifier,
+    },
+    Survive {
+        rounds: u16,
+    },
+    ReachTile {
+        location: (usize, usize),
+    },
+    /// Lose if the team member with this id is defeated
+    Protect {
+        character: Identifier,
+    },
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Any field left out, other than the ones a member can't do without, is taken from the archetype
+/// the member extends
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct TeamMember {
+    /// Defaults to the member's name
+    pub id: Option<Identifier>,
+    pub extends: Option<Identifier>,
     pub name: String,
-    pub race: Race,
-    pub base_health: LifeNumber,
+    pub race: Option<Race>,
+    pub base_health: Option<LifeNumber>,
     pub max_health: Option<LifeNumber>,
-    pub cards: Vec<CardId>,
+    pub cards: Option<Vec<Identifier>>,
     pub hand_size: Option<HandSize>,
     #[serde(default)]
     pub is_player: bool,
     pub image: Option<String>,
     pub location: (usize, usize),
     pub movement: Option<u64>,
+    /// Determines how early and, on a timeline, how often this member acts. Timelines treat a speed
+    /// of 0 as 1.
+    pub speed: Option<u64>,
+    /// How far this member can see. Members without a vision range see the whole board.
+    pub vision: Option<u64>,
+    /// Cards dropped onto the board when this member is defeated
+    pub loot: Option<Vec<Identifier>>,
+}
+
+impl TeamMember {
+    pub fn id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Takes each field the member leaves out from the archetype
+    fn inherit(&mut self, archetype: &Archetype) {
+        macro_rules! inherit {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field.is_none() {
+                        self.$field.clone_from(&archetype.$field);
+                    }
+                )*
+            };
+        }
+        inherit!(
+            race,
+            base_health,
+            max_health,
+            cards,
+            hand_size,
+            image,
+            movement,
+            speed,
+            vision,
+            loot
+        );
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A card lying on the board, waiting to be collected
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Pickup {
+    pub card: Identifier,
+    pub location: (usize, usize),
+}
+
+/// Where a collected card goes
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[serde(deny_unknown_fields)]
+pub enum CardPickup {
+    Deck,
+    Hand,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum Race {
     Human,
@@ -114,7 +654,27 @@ pub enum Target {
     Any,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+// Derived schemas leave out aliases, which would flag existing battle files as invalid
+impl JsonSchema for Target {
+    fn schema_name() -> String {
+        "Target".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(
+                ["me", "self", "others", "other", "any"]
+                    .map(Into::into)
+                    .to_vec(),
+            ),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 #[serde(untagged)]
 #[serde(deny_unknown_fields)]
 pub enum MaybeU64Range {
@@ -122,7 +682,7 @@ pub enum MaybeU64Range {
     Absolute(u64),
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 #[serde(deny_unknown_fields)]
 pub enum CardAction {
@@ -146,10 +706,21 @@ pub enum CardAction {
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl CardAction {
+    pub fn target(&self) -> &Target {
+        match self {
+            Self::Damage { target, .. } => target,
+            Self::Heal { target, .. } => target,
+            Self::GainAction { target, .. } => target,
+            Self::Move { target, .. } => target,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Card {
-    pub id: CardId,
+    pub id: Identifier,
     pub name: String,
     pub description: String,
     pub flavor: Option<String>,
@@ -159,12 +730,14 @@ pub struct Card {
 
 #[cfg(test)]
 mod tests {
-    use crate::battle_file::*;
+    use std::{fs, path::Path};
+
+    use crate::{battle_file::*, BattleFileError, BattleFormat, Diagnostics};
 
     use super::Battle;
 
     #[test]
-    fn test_deserialize() -> Result<(), String> {
+    fn test_deserialize() -> Result<(), Diagnostics> {
         let data = r#"{
             "title": "Example Game",
             "description": "Example Description",
@@ -176,7 +749,7 @@ mod tests {
             "board": { "width": 1, "height": 1 },
             "cards": [
                 {
-                    "id": 0,
+                    "id": "kick",
                     "name": "Kick",
                     "description": "description text",
                     "flavor": "flavor text",
@@ -199,7 +772,7 @@ mod tests {
                             "name": "Member 1",
                             "race": "Human",
                             "base_health": 10,
-                            "cards": [0],
+                            "cards": ["kick"],
                             "location": [0, 0]
                         }
                     ]
@@ -207,9 +780,10 @@ mod tests {
             ]
         }"#;
 
-        let battle: Battle = Battle::parse_from_str(data)?;
+        let battle: Battle = Battle::parse_from_str(data, BattleFormat::Json, None)?;
+        assert_eq!(battle.teams[0].members[0].cards, Some(vec!["kick".into()]));
         assert_eq!(
-            battle.cards[battle.teams[0].members[0].cards[0]].actions[0],
+            battle.cards[0].actions[0],
             CardAction::Damage {
                 target: Target::Others,
                 amount: MaybeU64Range::Absolute(123),
@@ -221,13 +795,13 @@ mod tests {
     }
 
     #[test]
-    fn test_multi_player_error() {
+    fn test_multi_player() {
         let data = r#"{
             "title": "Example Game",
             "description": "Example Description",
             "default_hand_size": 5,
             "cards": [],
-            "board": { "width": 1, "height": 1 },
+            "board": { "width": 2, "height": 1 },
             "teams": [
                 {
                     "name": "Team A",
@@ -246,19 +820,197 @@ mod tests {
                             "race": "Human",
                             "base_health": 10,
                             "cards": [],
-                            "location": [0, 0]
+                            "location": [1, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let battle = Battle::parse_from_str(data, BattleFormat::Json, None).unwrap();
+
+        assert_eq!(
+            battle
+                .teams
+                .iter()
+                .flat_map(|team| &team.members)
+                .filter(|member| member.is_player)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let data = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 5,
+            "cards": [
+                { "id": "kick", "name": "Kick", "description": "", "actions": [] },
+                { "id": "punch", "name": "Punch", "description": "", "actions": [] },
+                { "id": "kick", "name": "Kick again", "description": "", "actions": [] }
+            ],
+            "board": { "width": 2, "height": 1 },
+            "pickups": [{ "card": "sword", "location": [1, 0] }],
+            "walls": [[2, 0]],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member 1",
+                            "race": "Human",
+                            "base_health": 10,
+                            "max_health": 5,
+                            "image": "missing.png",
+                            "cards": ["kick", "shield"],
+                            "location": [1, 0]
                         }
                     ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [],
+                    "objectives": [{ "type": "defeat", "character": "Member 2" }]
                 }
             ]
         }"#;
+        let asset_directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
 
-        let maybe_battle = Battle::parse_from_str(data);
+        let diagnostics = Battle::check(data, BattleFormat::Json, Some(&asset_directory));
+        assert_eq!(
+            diagnostics.to_string(),
+            [
+                "7:17: warning: Card punch is never used ($.cards[1])",
+                "8:25: error: The id kick is used more than once ($.cards[2].id)",
+                "11:35: error: Unknown card sword ($.pickups[0].card)",
+                "11:56: error: Multiple entries found at 1, 0 ($.pickups[0].location)",
+                "12:23: error: 2, 0 is outside the board ($.walls[0])",
+                "21:43: error: Member 1 has a max health of 5, below its base health of 10 \
+                 ($.teams[0].members[0].max_health)",
+                "22:38: error: Image missing.png doesn't exist ($.teams[0].members[0].image)",
+                "23:47: error: Unknown card shield ($.teams[0].members[0].cards[1])",
+                "30:32: error: Team B has no members ($.teams[1].members)",
+                "31:69: error: Unknown team member Member 2 \
+                 ($.teams[1].objectives[0].character)",
+            ]
+            .join("\n")
+        );
+        assert!(diagnostics.has_errors());
+        assert_eq!(
+            Battle::parse_from_str(data, BattleFormat::Json, None)
+                .unwrap_err()
+                .0
+                .len(),
+            diagnostics.0.len() - 1,
+            "Images are only checked with an asset directory"
+        );
 
-        assert!(maybe_battle.is_err());
+        let diagnostics = Battle::check(r#"{ "title": 1 }"#, BattleFormat::Json, None);
+        assert_eq!(
+            diagnostics.to_string(),
+            "1:12: error: invalid type: integer `1`, expected a string ($)"
+        );
         assert_eq!(
-            maybe_battle.unwrap_err(),
-            "Multiple playable team members found."
+            diagnostics.0[0].error,
+            BattleFileError::Syntax("invalid type: integer `1`, expected a string".into())
         );
     }
+
+    #[test]
+    fn test_archetypes() {
+        let data = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 5,
+            "cards": [{ "id": "slash", "name": "Slash", "description": "", "actions": [] }],
+            "archetypes": [
+                { "id": "sailor", "race": "Human", "base_health": 5, "cards": [] },
+                { "id": "pirate", "extends": "sailor", "cards": ["slash"], "max_health": 8 },
+                { "id": "ghost", "extends": "phantom" },
+                { "id": "phantom", "extends": "ghost" }
+            ],
+            "board": { "width": 4, "height": 1 },
+            "teams": [
+                {
+                    "name": "Pirates",
+                    "members": [
+                        { "extends": "pirate", "name": "Pirate 1", "location": [0, 0] },
+                        {
+                            "extends": "pirate",
+                            "name": "Pirate 2",
+                            "base_health": 10,
+                            "location": [1, 0]
+                        },
+                        { "extends": "ghost", "name": "Ghost", "location": [2, 0] },
+                        { "name": "Nobody", "location": [3, 0] }
+                    ]
+                }
+            ]
+        }"#;
+
+        assert_eq!(
+            Battle::check(data, BattleFormat::Json, None).to_string(),
+            [
+                "9:45: error: Archetype cycle: phantom -> ghost -> phantom \
+                 ($.archetypes[2].extends)",
+                "10:47: error: Archetype cycle: ghost -> phantom -> ghost \
+                 ($.archetypes[3].extends)",
+                "19:40: error: Pirate 2 has a max health of 8, below its base health of 10 \
+                 ($.teams[0].members[1].extends)",
+                "24:38: error: Archetype cycle: ghost -> phantom -> ghost \
+                 ($.teams[0].members[2].extends)",
+                "25:25: error: Nobody has no race of its own or from an archetype it extends \
+                 ($.teams[0].members[3])",
+                "25:25: error: Nobody has no base_health of its own or from an archetype it \
+                 extends ($.teams[0].members[3])",
+                "25:25: error: Nobody has no cards of its own or from an archetype it extends \
+                 ($.teams[0].members[3])",
+            ]
+            .join("\n")
+        );
+
+        let mut battle: Battle = serde_json::from_str(data).unwrap();
+        let pirate = battle.with_archetypes(&battle.teams[0].members[0]).unwrap();
+        assert_eq!(pirate.name, "Pirate 1");
+        assert_eq!(pirate.base_health, Some(5));
+        assert_eq!(pirate.max_health, Some(8));
+        assert_eq!(
+            pirate.cards,
+            Some(vec!["slash".into()]),
+            "The nearest archetype wins"
+        );
+
+        battle.teams[0].members.truncate(1);
+        battle.merge_archetypes();
+        assert_eq!(
+            battle.teams[0].members[0].race.as_ref().unwrap(),
+            &Race::Human
+        );
+    }
+
+    #[test]
+    fn test_json_schema_is_current() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("battle.schema.json");
+        assert!(
+            fs::read_to_string(path)
+                .is_ok_and(|checked_in| checked_in.trim_end() == Battle::json_schema()),
+            "battle.schema.json is out of date. Regenerate it with `cargo run -- schema > \
+             battle.schema.json`."
+        );
+    }
+
+    #[test]
+    fn test_json_schema_accepts_aliases() {
+        let schema: serde_json::Value = serde_json::from_str(&Battle::json_schema()).unwrap();
+        let targets = schema["definitions"]["Target"]["enum"].as_array().unwrap();
+        assert_eq!(targets.len(), 5);
+        for target in targets {
+            assert!(
+                serde_json::from_value::<Target>(target.clone()).is_ok(),
+                "{target} is not a target"
+            );
+        }
+    }
 }
@@ -0,0 +1,387 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{ser::PrettyFormatter, Serializer, Value};
+
+use crate::{diagnostics::line_and_column, BattleFileError, Diagnostic, JsonPath};
+
+/// The languages battle files can be written in, chosen by their extension. They all describe the
+/// same battle, so enums like a member's race are written as strings, even in RON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BattleFormat {
+    Json,
+    /// Allows comments and multi-line strings, handy for long card descriptions
+    Toml,
+    /// Allows comments and trailing commas. Objects may be written as structs, like
+    /// `(name: "Punch")`, and optional values as `Some(1)`.
+    Ron,
+}
+
+impl BattleFormat {
+    /// The format of a file, by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "ron" => Some(Self::Ron),
+            _ => None,
+        }
+    }
+
+    /// Reads a file as a generic value, for migrating or converting to another format before it is
+    /// deserialized
+    pub fn parse(self, data: &str) -> Result<Value, Diagnostic> {
+        match self {
+            Self::Json => serde_json::from_str(data).map_err(|err| Diagnostic::syntax(&err)),
+            Self::Toml => toml::from_str(data).map_err(|err| toml_syntax_error(data, &err)),
+            // Going through RON's own value keeps structs and tuples from being mistaken for maps
+            Self::Ron => ron::from_str::<ron::Value>(data)
+                .map(|value| serde_json::to_value(value).unwrap())
+                .map_err(|err| {
+                    syntax_error(&err.code.to_string(), err.position.line, err.position.col)
+                }),
+        }
+    }
+
+    /// Reads a file straight into `T`, which keeps where any problem is
+    pub fn deserialize<T: DeserializeOwned>(self, data: &str) -> Result<T, Diagnostic> {
+        match self {
+            Self::Json => serde_json::from_str(data).map_err(|err| Diagnostic::syntax(&err)),
+            Self::Toml => toml::from_str(data).map_err(|err| toml_syntax_error(data, &err)),
+            // RON would expect enums in its own syntax, so it's read the same way as once migrated,
+            // though that loses where problems with the shape of the file are
+            Self::Ron => {
+                serde_json::from_value(self.parse(data)?).map_err(|err| Diagnostic::syntax(&err))
+            }
+        }
+    }
+
+    /// Writes a value out in the format. TOML and RON leave out nulls, which are the same as a
+    /// missing value, but TOML fails on anything else it can't hold, such as a null in a list.
+    pub fn serialize(self, value: &Value) -> Result<String, Diagnostic> {
+        Ok(match self {
+            Self::Json => {
+                let mut output = vec![];
+                let mut serializer =
+                    Serializer::with_formatter(&mut output, PrettyFormatter::with_indent(b"    "));
+                value.serialize(&mut serializer).unwrap();
+                output.push(b'\n');
+                String::from_utf8(output).unwrap()
+            }
+            Self::Toml if !value.is_object() => {
+                return Err(syntax_error("TOML files can only hold a table", 1, 1))
+            }
+            // TOML has no null to write
+            Self::Toml => toml::to_string_pretty(&without_nulls(value))
+                .map_err(|err| syntax_error(&format!("Unable to write TOML: {err}"), 1, 1))?,
+            Self::Ron => {
+                let mut output = String::new();
+                write_ron(value, 0, &mut output);
+                output.push('\n');
+                output
+            }
+        })
+    }
+}
+
+impl Display for BattleFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "JSON",
+            Self::Toml => "TOML",
+            Self::Ron => "RON",
+        })
+    }
+}
+
+fn syntax_error(message: &str, line: usize, column: usize) -> Diagnostic {
+    Diagnostic {
+        error: BattleFileError::Syntax(message.trim().replace('\n', ", ")),
+        path: JsonPath::default(),
+        line: line.max(1),
+        column: column.max(1),
+    }
+}
+
+fn toml_syntax_error(data: &str, err: &toml::de::Error) -> Diagnostic {
+    let (line, column) = line_and_column(data, err.span().map_or(0, |span| span.start));
+    syntax_error(err.message(), line, column)
+}
+
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => fields
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.clone(), without_nulls(value)))
+            .collect(),
+        Value::Array(items) => items.iter().map(without_nulls).collect(),
+        value => value.clone(),
+    }
+}
+
+/// Writes objects as structs, one field per line, and lists of plain values on a single line
+fn write_ron(value: &Value, depth: usize, output: &mut String) {
+    let indent = "    ".repeat(depth + 1);
+    match value {
+        Value::Null => output.push_str("None"),
+        Value::Bool(_) | Value::Number(_) => output.push_str(&value.to_string()),
+        Value::String(string) => output.push_str(&ron::to_string(string).unwrap()),
+        Value::Array(items)
+            if items
+                .iter()
+                .all(|item| !item.is_array() && !item.is_object()) =>
+        {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                write_ron(item, depth, output);
+            }
+            output.push(']');
+        }
+        Value::Array(items) => {
+            output.push_str("[\n");
+            for item in items {
+                output.push_str(&indent);
+                write_ron(item, depth + 1, output);
+                output.push_str(",\n");
+            }
+            output.push_str(&indent[4..]);
+            output.push(']');
+        }
+        // `()` would be read back as a unit value instead
+        Value::Object(fields) if fields.is_empty() => output.push_str("{}"),
+        Value::Object(fields) => {
+            output.push_str("(\n");
+            for (key, value) in fields.iter().filter(|(_, value)| !value.is_null()) {
+                output.push_str(&indent);
+                output.push_str(key);
+                output.push_str(": ");
+                write_ron(value, depth + 1, output);
+                output.push_str(",\n");
+            }
+            output.push_str(&indent[4..]);
+            output.push(')');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::BattleFormat;
+    use crate::{battle_file::Battle, Diagnostics};
+
+    const JSON_BATTLE: &str = r#"{
+        "title": "Example Game",
+        "description": "Example Description",
+        "default_hand_size": 1,
+        "introduction": [{ "h1": "Heading" }],
+        "board": { "width": 2, "height": 1 },
+        "cards": [
+            {
+                "id": "kick",
+                "name": "Kick",
+                "description": "A long description\nacross two lines",
+                "range": 1,
+                "actions": [{ "type": "damage", "target": "others", "amount": [1, 2] }]
+            }
+        ],
+        "teams": [
+            {
+                "name": "Team A",
+                "members": [
+                    {
+                        "name": "Member 1",
+                        "race": "Human",
+                        "base_health": 10,
+                        "cards": ["kick"],
+                        "location": [0, 0]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    const TOML_BATTLE: &str = r#"
+        title = "Example Game"
+        description = "Example Description"
+        default_hand_size = 1
+        introduction = [{ h1 = "Heading" }]
+        board = { width = 2, height = 1 }
+
+        # Cards are tables of their own
+        [[cards]]
+        id = "kick"
+        name = "Kick"
+        description = """
+A long description
+across two lines"""
+        range = 1
+        actions = [{ type = "damage", target = "others", amount = [1, 2] }]
+
+        [[teams]]
+        name = "Team A"
+
+        [[teams.members]]
+        name = "Member 1"
+        race = "Human"
+        base_health = 10
+        cards = ["kick"]
+        location = [0, 0]
+    "#;
+
+    const RON_BATTLE: &str = r#"Battle(
+        title: "Example Game",
+        description: "Example Description",
+        default_hand_size: 1,
+        introduction: Some([(h1: "Heading")]),
+        board: (width: 2, height: 1),
+        /* Descriptions can be raw strings */
+        cards: [
+            (
+                id: "kick",
+                name: "Kick",
+                description: r"A long description
+across two lines",
+                range: 1,
+                actions: [(type: "damage", target: "others", amount: (1, 2))],
+            ),
+        ],
+        teams: [
+            (
+                name: "Team A",
+                members: [
+                    (
+                        name: "Member 1",
+                        race: "Human", // Enums are strings like in JSON
+                        base_health: 10,
+                        cards: ["kick"],
+                        location: (0, 0),
+                    ),
+                ],
+            ),
+        ],
+    )"#;
+
+    const BATTLES: [(BattleFormat, &str); 3] = [
+        (BattleFormat::Json, JSON_BATTLE),
+        (BattleFormat::Toml, TOML_BATTLE),
+        (BattleFormat::Ron, RON_BATTLE),
+    ];
+
+    fn parse(format: BattleFormat, data: &str) -> Result<serde_json::Value, Diagnostics> {
+        let battle = Battle::parse_from_str(data, format, None)?;
+        Ok(serde_json::to_value(battle).unwrap())
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            BattleFormat::from_path(Path::new("battles/mines.toml")),
+            Some(BattleFormat::Toml)
+        );
+        assert_eq!(
+            BattleFormat::from_path(Path::new("mines.ron")),
+            Some(BattleFormat::Ron)
+        );
+        assert_eq!(BattleFormat::from_path(Path::new("mines.yaml")), None);
+        assert_eq!(BattleFormat::from_path(Path::new("mines")), None);
+    }
+
+    #[test]
+    fn test_formats() -> Result<(), Diagnostics> {
+        let expected = parse(BattleFormat::Json, JSON_BATTLE)?;
+        for (format, data) in BATTLES {
+            assert_eq!(parse(format, data)?, expected, "{format}");
+        }
+
+        let diagnostics: Vec<String> = BATTLES
+            .iter()
+            .map(|(format, data)| {
+                let data = data.replace(r#"["kick"]"#, r#"["kick", "bite"]"#);
+                Battle::check(&data, *format, None).to_string()
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "24:43: error: Unknown card bite ($.teams[0].members[0].cards[1])",
+                "25:26: error: Unknown card bite ($.teams[0].members[0].cards[1])",
+                "26:41: error: Unknown card bite ($.teams[0].members[0].cards[1])",
+            ],
+            "Problems are found and located the same way in every format"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let check = |(format, data): (BattleFormat, &str), from: &str, to: &str| {
+            Battle::check(&data.replace(from, to), format, None).to_string()
+        };
+        let [_, toml, ron] = BATTLES;
+        assert_eq!(
+            check(toml, "base_health = 10", "base_health ="),
+            "24:22: error: invalid string, expected `\"`, `'` ($)"
+        );
+        assert_eq!(
+            check(toml, "base_health = 10", "base_health = \"ten\""),
+            "24:23: error: invalid type: string \"ten\", expected u64 ($)"
+        );
+        assert_eq!(
+            check(ron, "base_health: 10,", "base_health: 10"),
+            "26:25: error: Expected comma ($)"
+        );
+        assert_eq!(
+            check(ron, "base_health: 10,", "base_health: \"ten\","),
+            "1:1: error: invalid type: string \"ten\", expected u64 ($)",
+            "RON is read as a generic value first, which loses where it was"
+        );
+    }
+
+    #[test]
+    fn test_convert() -> Result<(), Diagnostics> {
+        let expected = parse(BattleFormat::Json, JSON_BATTLE)?;
+        for (from, data) in BATTLES {
+            let value = from.parse(data).unwrap();
+            for (to, _) in BATTLES {
+                let converted = to.serialize(&value).unwrap();
+                assert_eq!(parse(to, &converted)?, expected, "{from} to {to}");
+            }
+        }
+
+        assert_eq!(
+            BattleFormat::Ron.serialize(&serde_json::json!({
+                "board": { "width": 2, "height": 1 },
+                "walls": [[0, 0]],
+                "turn_order": null,
+            }))
+            .unwrap(),
+            "(\n    board: (\n        width: 2,\n        height: 1,\n    ),\n    walls: [\n        [0, 0],\n    ],\n)\n",
+            "Objects are written as structs, leaving out missing values"
+        );
+        assert_eq!(
+            BattleFormat::Toml
+                .serialize(&serde_json::json!([1, 2]))
+                .unwrap_err()
+                .to_string(),
+            "1:1: error: TOML files can only hold a table ($)"
+        );
+        assert_eq!(
+            BattleFormat::Toml
+                .serialize(&serde_json::json!({ "walls": [[0, null]] }))
+                .unwrap_err()
+                .to_string(),
+            "1:1: error: Unable to write TOML: unsupported unit type ($)",
+            "Nulls can only be left out of tables"
+        );
+        Ok(())
+    }
+}
@@ -1,10 +1,11 @@
 use std::fmt::Display;
 
 use serde::Serialize;
+use ts_rs::TS;
 
-use crate::TemplateEntry;
+use crate::{TeamId, TemplateEntry};
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, TS)]
 pub enum BattleTextEntry {
     Id,
     Attack,
@@ -37,3 +38,20 @@ macro_rules! battle_markup {
 }
 
 pub type BattleText = Vec<TemplateEntry<BattleTextEntry>>;
+
+/// Something that happened in a battle. Teams that couldn't see every character it mentions when
+/// it happened never see it, which keeps history from giving away hidden enemies.
+pub struct HistoryEntry {
+    pub text: BattleText,
+    pub hidden_from: Vec<TeamId>,
+}
+
+impl HistoryEntry {
+    /// An entry that doesn't mention any characters, such as the start of a round
+    pub fn public(text: BattleText) -> Self {
+        Self {
+            text,
+            hidden_from: vec![],
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    battle_file::{validate_archetypes, validate_cards, Battle, Card, Identifier, Import, Library},
+    BattleFileError, BattleFormat, Diagnostics, JsonPath,
+};
+
+/// Reads an imported file, given its path relative to the asset directory
+pub(crate) type ImportLoader<'a> = dyn Fn(&Path) -> io::Result<String> + 'a;
+
+impl Battle {
+    /// Loads everything the battle imports into `imported`, along with any problems in the imported
+    /// files
+    pub(crate) fn resolve_imports(
+        &mut self,
+        load: &ImportLoader,
+    ) -> Vec<(BattleFileError, JsonPath)> {
+        let mut problems = vec![];
+        self.imported = resolve(
+            &self.imports,
+            &JsonPath::default().key("imports"),
+            load,
+            &mut vec![],
+            &mut problems,
+        );
+        problems
+    }
+
+    /// The imported cards the battle doesn't override, followed by the battle's own
+    pub fn all_cards(&self) -> impl Iterator<Item = &Card> {
+        self.imported
+            .cards
+            .iter()
+            .filter(|card| !self.cards.iter().any(|own| own.id == card.id))
+            .chain(&self.cards)
+    }
+}
+
+impl Library {
+    /// Finds the problems within the library itself, given everything it imports
+    fn validate(&self, imported: &Library) -> Vec<(BattleFileError, JsonPath)> {
+        let root = JsonPath::default();
+        let mut problems = vec![];
+        validate_cards(&self.cards, &root.key("cards"), &mut problems);
+
+        let card_exists = |card_id: &str| {
+            self.cards
+                .iter()
+                .chain(&imported.cards)
+                .any(|card| card.id == card_id)
+        };
+        let find_archetype = |id: &str| {
+            self.archetypes
+                .iter()
+                .chain(&imported.archetypes)
+                .find(|archetype| archetype.id == id)
+        };
+        validate_archetypes(
+            &self.archetypes,
+            &root.key("archetypes"),
+            &card_exists,
+            &find_archetype,
+            &mut problems,
+        );
+        problems
+    }
+}
+
+/// Loads each import, combining everything they define. `stack` holds the files currently being
+/// imported, outermost first, to catch cycles.
+fn resolve(
+    imports: &[Import],
+    path: &JsonPath,
+    load: &ImportLoader,
+    stack: &mut Vec<PathBuf>,
+    problems: &mut Vec<(BattleFileError, JsonPath)>,
+) -> Library {
+    let mut resolved = Library::default();
+    for (index, import) in imports.iter().enumerate() {
+        let path = path.index(index);
+        let unreadable = |reason: String| {
+            (
+                BattleFileError::ImportUnreadable {
+                    path: import.path.clone(),
+                    reason,
+                },
+                path.key("path"),
+            )
+        };
+        let file = match normalize(Path::new(&import.path)) {
+            Ok(file) => file,
+            Err(reason) => {
+                problems.push(unreadable(reason.into()));
+                continue;
+            }
+        };
+        if let Some(start) = stack.iter().position(|importing| importing == &file) {
+            let cycle = stack[start..]
+                .iter()
+                .chain([&file])
+                .map(|file| file.display().to_string())
+                .collect();
+            problems.push((BattleFileError::ImportCycle(cycle), path.key("path")));
+            continue;
+        }
+        let Some(format) = BattleFormat::from_path(&file) else {
+            problems.push(unreadable("the extension must be json, toml or ron".into()));
+            continue;
+        };
+        let data = match load(&file) {
+            Ok(data) => data,
+            Err(err) => {
+                problems.push(unreadable(err.to_string()));
+                continue;
+            }
+        };
+
+        stack.push(file);
+        let (library, diagnostics) = load_library(&data, format, load, stack);
+        let file = stack.pop().unwrap();
+        problems.extend(diagnostics.0.into_iter().map(|diagnostic| {
+            (
+                BattleFileError::InImport {
+                    path: import.path.clone(),
+                    diagnostic: Box::new(diagnostic),
+                },
+                path.key("path"),
+            )
+        }));
+        let Some(library) = library else {
+            continue;
+        };
+
+        let namespace = import.namespace.clone().unwrap_or_else(|| {
+            file.file_stem()
+                .map_or(String::new(), |stem| stem.to_string_lossy().into())
+        });
+        let namespaced = |id: &Identifier| format!("{namespace}.{id}");
+        for mut card in library.cards {
+            card.id = namespaced(&card.id);
+            if resolved.cards.iter().any(|existing| existing.id == card.id) {
+                problems.push((BattleFileError::DuplicateId(card.id), path.clone()));
+                continue;
+            }
+            resolved.cards.push(card);
+        }
+        for mut archetype in library.archetypes {
+            archetype.id = namespaced(&archetype.id);
+            archetype.extends = archetype.extends.as_ref().map(namespaced);
+            for cards in [&mut archetype.cards, &mut archetype.loot] {
+                for card_id in cards.iter_mut().flatten() {
+                    *card_id = namespaced(card_id);
+                }
+            }
+            if resolved
+                .archetypes
+                .iter()
+                .any(|existing| existing.id == archetype.id)
+            {
+                problems.push((BattleFileError::DuplicateId(archetype.id), path.clone()));
+                continue;
+            }
+            resolved.archetypes.push(archetype);
+        }
+    }
+    resolved
+}
+
+/// Parses a library along with everything it imports, keeping ids relative to the library. Its own
+/// cards and archetypes replace imported ones with the same id.
+fn load_library(
+    data: &str,
+    format: BattleFormat,
+    load: &ImportLoader,
+    stack: &mut Vec<PathBuf>,
+) -> (Option<Library>, Diagnostics) {
+    let mut library: Library = match format.deserialize(data) {
+        Ok(library) => library,
+        Err(diagnostic) => return (None, Diagnostics(vec![diagnostic])),
+    };
+    let mut problems = vec![];
+    let imported = resolve(
+        &library.imports,
+        &JsonPath::default().key("imports"),
+        load,
+        stack,
+        &mut problems,
+    );
+    problems.extend(library.validate(&imported));
+
+    library.cards = with_overrides(imported.cards, library.cards, |card| &card.id);
+    library.archetypes = with_overrides(imported.archetypes, library.archetypes, |archetype| {
+        &archetype.id
+    });
+    (Some(library), Diagnostics::locate(data, format, problems))
+}
+
+/// Combines imported definitions with a file's own, which replace imported ones with the same id
+fn with_overrides<T>(imported: Vec<T>, own: Vec<T>, id: impl Fn(&T) -> &Identifier) -> Vec<T> {
+    let mut combined: Vec<T> = imported
+        .into_iter()
+        .filter(|imported| !own.iter().any(|own| id(own) == id(imported)))
+        .collect();
+    combined.extend(own);
+    combined
+}
+
+/// Resolves `.` and `..` so the same file is always found at the same path. Paths that could reach
+/// outside the asset directory are refused, with the reason why.
+fn normalize(path: &Path) -> Result<PathBuf, &'static str> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return Err("the path can't leave the asset directory");
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err("the path must be relative to the asset directory")
+            }
+            Component::Normal(name) => normalized.push(name),
+        }
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io, path::Path};
+
+    use crate::{battle_file::Battle, BattleFormat, Diagnostics};
+
+    const BATTLE_HEADER: &str = r#"
+        "title": "Example Game",
+        "description": "Example Description",
+        "default_hand_size": 1,
+        "board": { "width": 1, "height": 1 },
+        "teams": []
+    "#;
+
+    fn parse(data: &str, files: &[(&str, &str)]) -> (Option<Battle>, Diagnostics) {
+        let files: HashMap<&str, &str> = files.iter().copied().collect();
+        let load = |path: &Path| {
+            files
+                .get(path.to_str().unwrap())
+                .map(|data| data.to_string())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        };
+        Battle::parse_with_loader(data, BattleFormat::Json, None, &load)
+    }
+
+    #[test]
+    fn test_imports() {
+        let data = format!(
+            r#"{{
+                "imports": [{{ "path": "./cards/basics.json" }}],
+                "cards": [
+                    {{ "id": "basics.punch", "name": "Strong Punch", "description": "", "actions": [] }}
+                ],
+                {BATTLE_HEADER}
+            }}"#
+        );
+        let basics = r#"{
+            "imports": [{ "path": "cards/common.toml", "namespace": "shared" }],
+            "cards": [
+                { "id": "punch", "name": "Punch", "description": "", "actions": [] },
+                { "id": "shared.rest", "name": "Long Rest", "description": "", "actions": [] }
+            ],
+            "archetypes": [{ "id": "brawler", "cards": ["punch", "shared.kick"] }]
+        }"#;
+        let common = r#"
+            cards = [
+                { id = "kick", name = "Kick", description = "", actions = [] },
+                { id = "rest", name = "Rest", description = "", actions = [] },
+            ]
+        "#;
+        let (battle, diagnostics) = parse(
+            &data,
+            &[("cards/basics.json", basics), ("cards/common.toml", common)],
+        );
+        assert!(!diagnostics.has_errors(), "{diagnostics}");
+
+        let battle = battle.unwrap();
+        assert_eq!(
+            battle
+                .all_cards()
+                .map(|card| (card.id.as_str(), card.name.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("basics.shared.kick", "Kick"),
+                ("basics.shared.rest", "Long Rest"),
+                ("basics.punch", "Strong Punch"),
+            ],
+            "Cards are namespaced and the importing file's own override imported ones"
+        );
+        let brawler = &battle.imported.archetypes[0];
+        assert_eq!(brawler.id, "basics.brawler");
+        assert_eq!(
+            brawler.cards.as_deref(),
+            Some(&["basics.punch".into(), "basics.shared.kick".into()][..])
+        );
+    }
+
+    #[test]
+    fn test_import_problems() {
+        let data = format!(
+            r#"{{
+                "imports": [
+                    {{ "path": "a.json" }},
+                    {{ "path": "missing.json" }},
+                    {{ "path": "broken.json" }},
+                    {{ "path": "/etc/a.json" }},
+                    {{ "path": "cards/../../a.json" }}
+                ],
+                "cards": [],
+                {BATTLE_HEADER}
+            }}"#
+        );
+        let a = r#"{ "imports": [{ "path": "b.json" }] }"#;
+        let b = r#"{
+            "imports": [{ "path": "a.json" }],
+            "archetypes": [{ "id": "brawler", "cards": ["punch"] }]
+        }"#;
+        let broken = r#"{ "cards": 1 }"#;
+        let (battle, diagnostics) = parse(
+            &data,
+            &[("a.json", a), ("b.json", b), ("broken.json", broken)],
+        );
+        assert!(battle.is_some());
+        assert_eq!(
+            diagnostics.to_string(),
+            [
+                "3:31: error: a.json:1:25: b.json:2:35: Import cycle: a.json -> b.json -> a.json \
+                 ($.imports[0].path) ($.imports[0].path)",
+                "3:31: error: a.json:1:25: b.json:3:57: Unknown card punch \
+                 ($.archetypes[0].cards[0]) ($.imports[0].path)",
+                "4:31: error: Unable to import missing.json: not found ($.imports[1].path)",
+                "5:31: error: broken.json:1:12: invalid type: integer `1`, expected a sequence \
+                 ($) ($.imports[2].path)",
+                "6:31: error: Unable to import /etc/a.json: the path must be relative to the \
+                 asset directory ($.imports[3].path)",
+                "7:31: error: Unable to import cards/../../a.json: the path can't leave the \
+                 asset directory ($.imports[4].path)",
+            ]
+            .join("\n")
+        );
+    }
+}
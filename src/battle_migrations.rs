@@ -0,0 +1,270 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{BattleFileError, BattleFormat, Diagnostics, JsonPath};
+
+/// The version of the battle file format this build reads and writes
+pub const CURRENT_FORMAT_VERSION: u64 = 2;
+
+/// Files from before the format was versioned don't have a version
+const UNVERSIONED_FORMAT_VERSION: u64 = 1;
+
+/// Upgrades a battle file from the version before `version`
+struct Migration {
+    version: u64,
+    migrate: fn(&mut Value),
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 2,
+    migrate: string_card_ids,
+}];
+
+/// Upgrades a battle file to the current version of the format, returning the version it was
+/// written in. The version itself is left as it was.
+pub fn migrate(battle: &mut Value) -> Result<u64, BattleFileError> {
+    let version = match battle.get("format_version") {
+        None => UNVERSIONED_FORMAT_VERSION,
+        Some(version) => {
+            u64::deserialize(version).map_err(|err| BattleFileError::Syntax(err.to_string()))?
+        }
+    };
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(BattleFileError::UnsupportedVersion {
+            found: version,
+            supported: CURRENT_FORMAT_VERSION,
+        });
+    }
+    for migration in MIGRATIONS {
+        if migration.version > version {
+            (migration.migrate)(battle);
+        }
+    }
+    Ok(version)
+}
+
+/// Rewrites a battle file in the current version of the format, or `None` if it already is. Comments
+/// in TOML and RON files aren't kept.
+pub fn upgrade(data: &str, format: BattleFormat) -> Result<Option<String>, Diagnostics> {
+    let mut battle = format
+        .parse(data)
+        .map_err(|diagnostic| Diagnostics(vec![diagnostic]))?;
+    let version = migrate(&mut battle).map_err(|error| {
+        Diagnostics::locate(
+            data,
+            format,
+            vec![(error, JsonPath::default().key("format_version"))],
+        )
+    })?;
+    if battle.get("format_version").is_some() && version == CURRENT_FORMAT_VERSION {
+        return Ok(None);
+    }
+
+    let Value::Object(fields) = battle else {
+        return Ok(None);
+    };
+    let mut upgraded = Map::new();
+    upgraded.insert("format_version".into(), CURRENT_FORMAT_VERSION.into());
+    upgraded.extend(
+        fields
+            .into_iter()
+            .filter(|(key, _)| key != "format_version"),
+    );
+    format
+        .serialize(&Value::Object(upgraded))
+        .map(Some)
+        .map_err(|diagnostic| Diagnostics(vec![diagnostic]))
+}
+
+/// Version 2 refers to cards by string ids, made from their names, instead of by their position in
+/// the list of cards
+fn string_card_ids(battle: &mut Value) {
+    let mut ids = HashMap::new();
+    let mut taken = HashSet::new();
+    for card in battle
+        .get_mut("cards")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+    {
+        let Some(old_id) = card.get("id").and_then(Value::as_u64) else {
+            continue;
+        };
+        let name = card.get("name").and_then(Value::as_str).unwrap_or_default();
+        let mut id = name_to_id(name);
+        if id.is_empty() || !taken.insert(id.clone()) {
+            // Numbered after the card's old id, or the next number that is free
+            id = (old_id..)
+                .map(|number| format!("{id}-{number}").trim_start_matches('-').to_string())
+                .find(|id| !taken.contains(id))
+                .unwrap();
+            taken.insert(id.clone());
+        }
+        card["id"] = id.clone().into();
+        ids.insert(old_id, id);
+    }
+
+    let mut replace = |card_id: &mut Value| {
+        if let Some(old_id) = card_id.as_u64() {
+            *card_id = ids
+                .get(&old_id)
+                .cloned()
+                .unwrap_or_else(|| old_id.to_string())
+                .into();
+        }
+    };
+    let members = battle
+        .get_mut("teams")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|team| team.get_mut("members").and_then(Value::as_array_mut))
+        .flatten();
+    for member in members {
+        for key in ["cards", "loot"] {
+            let cards = member.get_mut(key).and_then(Value::as_array_mut);
+            cards.into_iter().flatten().for_each(&mut replace);
+        }
+    }
+    let pickups = battle.get_mut("pickups").and_then(Value::as_array_mut);
+    for pickup in pickups.into_iter().flatten() {
+        if let Some(card_id) = pickup.get_mut("card") {
+            replace(card_id);
+        }
+    }
+}
+
+/// Makes an id like "reloadable-slingshot" from a name like "Reloadable Slingshot"
+fn name_to_id(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{migrate, upgrade, CURRENT_FORMAT_VERSION};
+    use crate::{battle_file::Battle, BattleFormat, Diagnostics};
+
+    /// Cards were referred to by their position before version 2
+    const VERSION_1_BATTLE: &str = r#"{
+        "title": "Example Game",
+        "description": "Example Description",
+        "default_hand_size": 5,
+        "board": { "width": 2, "height": 1 },
+        "cards": [
+            { "id": 0, "name": "Big Punch!", "description": "", "actions": [] },
+            { "id": 1, "name": "Big punch", "description": "", "actions": [] }
+        ],
+        "pickups": [{ "card": 1, "location": [1, 0] }],
+        "teams": [
+            {
+                "name": "Team A",
+                "members": [
+                    {
+                        "name": "Member 1",
+                        "race": "Human",
+                        "base_health": 10,
+                        "cards": [0, 1],
+                        "location": [0, 0]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_migrate() -> Result<(), Diagnostics> {
+        let battle = Battle::parse_from_str(VERSION_1_BATTLE, BattleFormat::Json, None)?;
+        let ids: Vec<&str> = battle.cards.iter().map(|card| card.id.as_str()).collect();
+        assert_eq!(ids, ["big-punch", "big-punch-1"]);
+        assert_eq!(
+            battle.teams[0].members[0].cards,
+            Some(vec!["big-punch".into(), "big-punch-1".into()])
+        );
+        assert_eq!(battle.pickups[0].card, "big-punch-1");
+
+        let data = VERSION_1_BATTLE.replace("\"cards\": [0, 1]", "\"cards\": [0, 7]");
+        assert_eq!(
+            Battle::check(&data, BattleFormat::Json, None).to_string(),
+            "19:38: error: Unknown card 7 ($.teams[0].members[0].cards[1])",
+            "Cards that don't exist keep their old id"
+        );
+
+        let mut battle = json!({
+            "cards": [
+                { "id": 0, "name": "A" },
+                { "id": 1, "name": "A 2" },
+                { "id": 2, "name": "A" }
+            ]
+        });
+        migrate(&mut battle).unwrap();
+        assert_eq!(
+            battle["cards"],
+            json!([
+                { "id": "a", "name": "A" },
+                { "id": "a-2", "name": "A 2" },
+                { "id": "a-3", "name": "A" }
+            ]),
+            "Numbered ids skip ones other cards already have"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade() -> Result<(), Diagnostics> {
+        let upgraded = upgrade(VERSION_1_BATTLE, BattleFormat::Json)?.unwrap();
+        assert!(upgraded.starts_with(&format!(
+            "{{\n    \"format_version\": {CURRENT_FORMAT_VERSION},\n    \"title\": \"Example Game\",\n"
+        )));
+        assert_eq!(
+            Battle::parse_from_str(&upgraded, BattleFormat::Json, None)?.format_version,
+            Some(CURRENT_FORMAT_VERSION)
+        );
+        assert_eq!(
+            upgrade(&upgraded, BattleFormat::Json)?,
+            None,
+            "Upgrading again changes nothing"
+        );
+
+        let newer = upgraded.replace(
+            &format!("\"format_version\": {CURRENT_FORMAT_VERSION}"),
+            "\"format_version\": 100",
+        );
+        assert_eq!(
+            upgrade(&newer, BattleFormat::Json).unwrap_err().to_string(),
+            "2:23: error: Format version 100 is newer than the latest supported version, 2 \
+             ($.format_version)"
+        );
+        assert_eq!(
+            Battle::check(&newer, BattleFormat::Json, None).to_string(),
+            upgrade(&newer, BattleFormat::Json).unwrap_err().to_string()
+        );
+
+        let not_a_number = upgraded.replace(
+            &format!("\"format_version\": {CURRENT_FORMAT_VERSION}"),
+            &format!("\"format_version\": \"{CURRENT_FORMAT_VERSION}\""),
+        );
+        assert_eq!(
+            upgrade(&not_a_number, BattleFormat::Json)
+                .unwrap_err()
+                .to_string(),
+            "2:23: error: invalid type: string \"2\", expected u64 ($.format_version)"
+        );
+        assert_eq!(
+            Battle::check(&not_a_number, BattleFormat::Json, None).to_string(),
+            upgrade(&not_a_number, BattleFormat::Json)
+                .unwrap_err()
+                .to_string()
+        );
+
+        Ok(())
+    }
+}
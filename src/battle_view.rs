@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::{
+    battle_file, Battle, BattleOutcome, BattleText, BoardItem, Card, CardId, CardPickup, Character,
+    CharacterId, CharacterRace, DeathRule, Grid, GridLocation, Health, Team, TeamId,
+};
+
+type HandSize = battle_file::HandSize;
+type StoryCard = battle_file::StoryCard;
+
+/// The parts of a [`Character`] a viewer is allowed to see. Cards held by characters on other teams
+/// are hidden.
+#[derive(Serialize, TS)]
+#[ts(rename = "Character")]
+pub struct CharacterView<'battle> {
+    pub id: CharacterId,
+    pub name: &'battle str,
+    pub race: &'battle CharacterRace,
+    pub hand: Option<&'battle Vec<CardId>>,
+    pub deck: Option<&'battle Vec<CardId>>,
+    pub health: Health,
+    pub max_health: Health,
+    pub remaining_actions: u64,
+    pub hand_size: HandSize,
+    pub image: Option<&'battle str>,
+    pub movement: u64,
+    pub default_movement: u64,
+    pub loot: Option<&'battle Vec<CardId>>,
+    pub speed: u64,
+    pub action_points: u64,
+    pub vision: Option<u64>,
+}
+
+impl<'battle> CharacterView<'battle> {
+    fn new(character: &'battle Character, is_visible: bool) -> Self {
+        let reveal = |cards: &'battle Vec<CardId>| if is_visible { Some(cards) } else { None };
+        Self {
+            id: character.id,
+            name: &character.name,
+            race: &character.race,
+            hand: reveal(&character.hand),
+            deck: reveal(&character.deck),
+            health: character.health,
+            max_health: character.max_health,
+            remaining_actions: character.remaining_actions,
+            hand_size: character.hand_size,
+            image: character.image.as_deref(),
+            movement: character.movement,
+            default_movement: character.default_movement,
+            loot: reveal(&character.loot),
+            speed: character.speed,
+            action_points: character.action_points,
+            vision: character.vision,
+        }
+    }
+}
+
+/// The parts of the board a viewer can see. Walls are always shown but anything else on a tile
+/// outside the viewer's vision is left out.
+#[derive(Serialize, TS)]
+#[ts(rename = "Board")]
+pub struct BoardView {
+    pub grid: Grid<BoardItem>,
+    /// Whether each tile is currently in sight, indexed by row and then column. `None` if nothing
+    /// is hidden from the viewer.
+    pub visible: Option<Vec<Vec<bool>>>,
+}
+
+impl BoardView {
+    fn new(battle: &Battle, visible_locations: Option<&HashSet<GridLocation>>) -> Self {
+        let mut grid = battle.board.grid.clone();
+        let visible = visible_locations.map(|visible_locations| {
+            (0..grid.height())
+                .map(|y| {
+                    (0..grid.width())
+                        .map(|x| visible_locations.contains(&GridLocation { x, y }))
+                        .collect::<Vec<bool>>()
+                })
+                .collect::<Vec<Vec<bool>>>()
+        });
+        if let Some(visible) = &visible {
+            for (y, row) in visible.iter().enumerate() {
+                for (x, is_visible) in row.iter().enumerate() {
+                    if !is_visible && grid.get(x, y) != Some(&BoardItem::Wall) {
+                        grid.clear(x, y);
+                    }
+                }
+            }
+        }
+        Self { grid, visible }
+    }
+}
+
+/// A projection of a [`Battle`] containing only what one viewer is allowed to see. This is the only
+/// form in which a battle is serialized for actors and clients.
+#[derive(Serialize, TS)]
+#[ts(rename = "Battle")]
+pub struct BattleView<'battle> {
+    #[ts(type = "Record<CharacterId, Character>")]
+    pub characters: HashMap<CharacterId, CharacterView<'battle>>,
+    pub introduction: Option<&'battle StoryCard>,
+    pub teams: &'battle Vec<Team>,
+    pub history: Vec<&'battle BattleText>,
+    pub round: u16,
+    #[ts(type = "Record<CardId, Card>")]
+    pub cards: &'battle HashMap<CardId, Card>,
+    pub default_turn_actions: u64,
+    pub card_pickup: &'battle CardPickup,
+    pub death_rule: &'battle DeathRule,
+    pub board: BoardView,
+    pub max_rounds: Option<u16>,
+    pub stalemate_rounds: u16,
+    pub rounds_without_health_change: u16,
+    pub outcome: Option<&'battle BattleOutcome>,
+    pub turn_order: Vec<CharacterId>,
+}
+
+impl<'battle> BattleView<'battle> {
+    /// Builds the view for a member of `viewer`, or for an outsider that sees no private
+    /// information if `None`. Enemies outside the viewer's vision are left out entirely, and
+    /// outsiders only see what every team can.
+    pub fn new(battle: &'battle Battle, viewer: Option<TeamId>) -> Self {
+        // Outsiders see no more than every team still in the battle can, so watching can't be used
+        // to see past a team's fog
+        let team_visibility = match viewer {
+            Some(team_id) => vec![(team_id, battle.get_visible_locations(team_id))],
+            None => battle.get_visible_locations_by_team(),
+        };
+        let visible_locations = team_visibility
+            .iter()
+            .filter_map(|(_, visible)| visible.clone())
+            .reduce(|visible, team_visible| &visible & &team_visible);
+        let is_visible = |character_id: &CharacterId| {
+            team_visibility.iter().all(|(team_id, visible)| {
+                battle.is_visible_in(*team_id, character_id, visible.as_ref())
+            })
+        };
+        Self {
+            characters: battle
+                .actors
+                .iter()
+                .filter(|(_, actor)| is_visible(actor.get_character_id()))
+                .map(|(team_id, actor)| {
+                    let character = battle.get_character(actor.as_ref());
+                    (
+                        character.id,
+                        CharacterView::new(character, viewer == Some(*team_id)),
+                    )
+                })
+                .collect(),
+            introduction: battle.introduction.as_ref(),
+            teams: &battle.teams,
+            history: battle
+                .history
+                .iter()
+                .filter(|entry| match viewer {
+                    Some(team_id) => !entry.hidden_from.contains(&team_id),
+                    None => entry.hidden_from.is_empty(),
+                })
+                .map(|entry| &entry.text)
+                .collect(),
+            round: battle.round,
+            cards: &battle.cards,
+            default_turn_actions: battle.default_turn_actions,
+            card_pickup: &battle.card_pickup,
+            death_rule: &battle.death_rule,
+            board: BoardView::new(battle, visible_locations.as_ref()),
+            max_rounds: battle.max_rounds,
+            stalemate_rounds: battle.stalemate_rounds,
+            rounds_without_health_change: battle.rounds_without_health_change,
+            outcome: battle.outcome.as_ref(),
+            turn_order: battle
+                .turn_order
+                .iter()
+                .filter(|id| is_visible(id))
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{
+        battle_markup, Battle, BattleFormat, BattleView, DefaultRandomProvider, Diagnostics,
+        HistoryEntry, TeamId,
+    };
+
+    #[tokio::test]
+    async fn test_hides_other_teams_cards() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 2, "height": 1 },
+            "cards": [
+                {
+                    "id": "rest",
+                    "name": "Rest",
+                    "description": "Do nothing",
+                    "actions": []
+                }
+            ],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["rest"],
+                            "location": [0, 0]
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": ["rest"],
+                            "location": [1, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+
+        let view = serde_json::to_value(BattleView::new(&battle, Some(TeamId::new(0)))).unwrap();
+        assert_eq!(view["characters"]["0"]["deck"], json!([0]));
+        assert_eq!(view["characters"]["1"]["deck"], Value::Null);
+        assert_eq!(view["characters"]["1"]["hand"], Value::Null);
+        assert_eq!(view["characters"]["1"]["name"], json!("Member B"));
+
+        let view = serde_json::to_value(BattleView::new(&battle, None)).unwrap();
+        assert_eq!(view["characters"]["0"]["deck"], Value::Null);
+        assert_eq!(view["characters"]["1"]["deck"], Value::Null);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hides_unseen_enemies() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 4, "height": 1 },
+            "walls": [[1, 0]],
+            "cards": [],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [0, 0],
+                            "vision": 3
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [3, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        battle.history = vec![
+            HistoryEntry::public(battle_markup!["--- Round 1"]),
+            HistoryEntry {
+                text: battle_markup![@id(&"Member B"), " took no action"],
+                hidden_from: vec![TeamId::new(0)],
+            },
+        ];
+
+        let view = serde_json::to_value(BattleView::new(&battle, Some(TeamId::new(0)))).unwrap();
+        assert_eq!(view["characters"]["1"], Value::Null);
+        assert_eq!(
+            view["history"],
+            json!([[{ "Text": "--- Round 1" }]]),
+            "What hidden enemies did is left out"
+        );
+        assert_eq!(
+            view["board"]["grid"]["members"],
+            json!([[{ "Character": 0 }, "Wall", null, null]])
+        );
+        assert_eq!(
+            view["board"]["visible"],
+            json!([[true, true, false, false]])
+        );
+
+        let view = serde_json::to_value(BattleView::new(&battle, Some(TeamId::new(1)))).unwrap();
+        assert_eq!(view["characters"]["0"]["name"], json!("Member A"));
+        assert_eq!(view["history"].as_array().unwrap().len(), 2);
+        assert_eq!(view["board"]["visible"], Value::Null);
+
+        let view = serde_json::to_value(BattleView::new(&battle, None)).unwrap();
+        assert_eq!(
+            view["characters"]["1"],
+            Value::Null,
+            "Spectators only see what every team can"
+        );
+        assert_eq!(view["characters"]["0"]["name"], json!("Member A"));
+        assert_eq!(view["history"], json!([[{ "Text": "--- Round 1" }]]));
+        assert_eq!(
+            view["board"]["grid"]["members"],
+            json!([[{ "Character": 0 }, "Wall", null, null]])
+        );
+
+        Ok(())
+    }
+}
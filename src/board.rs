@@ -1,14 +1,32 @@
 use serde::Serialize;
+use ts_rs::TS;
 
 use crate::{CardId, CharacterId, Grid, GridDimension, GridLocation};
 
-#[derive(Serialize, PartialEq, Debug)]
+#[derive(Serialize, PartialEq, Debug, Clone, TS)]
 pub enum BoardItem {
     Character(CharacterId),
     Card(CardId),
+    Remains(CharacterId),
+    Wall,
 }
 
-#[derive(Serialize)]
+impl BoardItem {
+    /// Whether a character may move onto a tile holding this item
+    pub fn is_passable(&self) -> bool {
+        match self {
+            BoardItem::Card(_) | BoardItem::Remains(_) => true,
+            BoardItem::Character(_) | BoardItem::Wall => false,
+        }
+    }
+
+    /// Whether this item stops characters from seeing past it
+    pub fn blocks_sight(&self) -> bool {
+        matches!(self, BoardItem::Wall)
+    }
+}
+
+#[derive(Serialize, Clone)]
 pub struct Board {
     pub grid: Grid<BoardItem>,
 }
@@ -24,6 +42,7 @@ impl Board {
         self.grid.find(|entry| entry == board_item)
     }
 
+    /// Finds characters within `range` of `location`, including any remains of defeated characters
     pub fn find_chars_in_range(
         &self,
         location: GridLocation,
@@ -31,11 +50,11 @@ impl Board {
     ) -> Vec<CharacterId> {
         self.grid
             .find_in_range(location, range, |entry| {
-                matches!(entry, BoardItem::Character(_))
+                matches!(entry, BoardItem::Character(_) | BoardItem::Remains(_))
             })
             .iter()
             .map(|location| match self.grid.get(location.x, location.y) {
-                Some(BoardItem::Character(id)) => *id,
+                Some(BoardItem::Character(id) | BoardItem::Remains(id)) => *id,
                 _ => {
                     unreachable!("Only valid characters available due to filterin in find_in_range")
                 }
@@ -56,16 +75,27 @@ impl Board {
             self.grid.shortest_path(
                 GridLocation { x: ax, y: ay },
                 GridLocation { x: bx, y: by },
-                |item| match item {
-                    BoardItem::Card(_) => true,
-                    character @ BoardItem::Character(_) => &b == character,
-                },
+                |item| item.is_passable() || &b == item,
             )
         } else {
             None
         }
     }
 
+    /// Finds every location visible from `location`. A range of `None` sees across the whole board.
+    pub fn find_visible(&self, location: &GridLocation, range: Option<u64>) -> Vec<GridLocation> {
+        let range = range.map_or(self.grid.width() + self.grid.height(), |range| {
+            range.try_into().unwrap_or(GridDimension::MAX)
+        });
+        self.grid
+            .find_visible(location, range, BoardItem::blocks_sight)
+    }
+
+    /// Finds the closest unoccupied location to `location`
+    pub fn find_nearest_empty(&self, location: &GridLocation) -> Option<GridLocation> {
+        self.grid.find_nearest_empty(location)
+    }
+
     pub fn require_distance(&self, a: BoardItem, b: BoardItem) -> u64 {
         self.distance(a, b).unwrap()
     }
@@ -1,12 +1,13 @@
 use serde::Serialize;
+use ts_rs::TS;
 
 use crate::{battle_file, DeclareWrappedType, RandomProvider};
 
-DeclareWrappedType!(CardId, id, battle_file::CardId);
+DeclareWrappedType!(CardId, id, usize);
 
 pub type LifeNumber = battle_file::LifeNumber;
 
-#[derive(Debug, PartialEq, Clone, Serialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
 pub struct U64Range(pub u64, pub u64);
 
 impl U64Range {
@@ -15,7 +16,7 @@ impl U64Range {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
 pub enum Target {
     Me,
     Others,
@@ -29,7 +30,7 @@ impl Target {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
 pub enum CardAction {
     Damage {
         target: Target,
@@ -62,7 +63,16 @@ impl CardAction {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// Where a card picked up from the board is placed
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
+pub enum CardPickup {
+    /// The card joins the deck and can be drawn in future rounds
+    Deck,
+    /// The card is immediately playable but is discarded with the rest of the hand
+    Hand,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
 pub struct Card {
     pub id: CardId,
     pub name: String,
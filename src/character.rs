@@ -4,6 +4,7 @@ use std::{
 };
 
 use serde::Serialize;
+use ts_rs::TS;
 
 use crate::*;
 
@@ -11,7 +12,7 @@ type HandSize = battle_file::HandSize;
 
 DeclareWrappedType!(CharacterId, id, usize);
 
-#[derive(Serialize)]
+#[derive(Serialize, TS)]
 pub enum CharacterRace {
     Human,
     Machine,
@@ -35,7 +36,6 @@ impl SubAssign<Attack> for Health {
     }
 }
 
-#[derive(Serialize)]
 pub struct Character {
     pub id: CharacterId,
     pub name: String,
@@ -49,6 +49,12 @@ pub struct Character {
     pub image: Option<String>,
     pub movement: u64,
     pub default_movement: u64,
+    pub loot: Vec<CardId>,
+    pub speed: u64,
+    /// How far this character can see, or `None` for no limit
+    pub vision: Option<u64>,
+    /// Points accumulated towards the next turn on a timeline
+    pub action_points: u64,
 }
 
 impl Character {
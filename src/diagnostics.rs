@@ -0,0 +1,534 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+    battle_file::{Identifier, LifeNumber},
+    BattleFormat,
+};
+
+/// How serious a problem in a battle file is. Only errors keep a battle from loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found in a battle file
+#[derive(Debug, Clone, PartialEq)]
+pub enum BattleFileError {
+    /// The file can't be read in its format or isn't shaped like a battle file
+    Syntax(String),
+    /// Another card or team member already has the id
+    DuplicateId(Identifier),
+    MissingRange {
+        card_id: Identifier,
+    },
+    UnknownCard(Identifier),
+    UnknownArchetype(Identifier),
+    /// The archetypes that extend each other, ending with the one that was already extended
+    ArchetypeCycle(Vec<Identifier>),
+    /// Neither the member nor any archetype it extends sets a field every member needs
+    MissingField {
+        name: String,
+        field: &'static str,
+    },
+    /// No team member has the id
+    UnknownCharacter(Identifier),
+    EmptyTeam {
+        team: String,
+    },
+    OutOfBounds {
+        x: usize,
+        y: usize,
+    },
+    Overlap {
+        x: usize,
+        y: usize,
+    },
+    /// The image isn't in the asset directory
+    MissingImage {
+        image: String,
+    },
+    MaxHealthBelowBase {
+        name: String,
+        base_health: LifeNumber,
+        max_health: LifeNumber,
+    },
+    /// No one starts with, drops, or can pick up the card
+    UnusedCard(Identifier),
+    /// Human players can only join battles hosted by a web server
+    ServerRequired,
+    /// The file was written for a newer version of the format than this one supports
+    UnsupportedVersion {
+        found: u64,
+        supported: u64,
+    },
+    ImportUnreadable {
+        path: String,
+        reason: String,
+    },
+    /// The files that import each other, ending with the one that was already being imported
+    ImportCycle(Vec<String>),
+    /// A problem in an imported file, which may have been imported by another import in turn
+    InImport {
+        path: String,
+        diagnostic: Box<Diagnostic>,
+    },
+}
+
+impl BattleFileError {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnusedCard(_) => Severity::Warning,
+            Self::InImport { diagnostic, .. } => diagnostic.severity(),
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl Display for BattleFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(message) => f.write_str(message),
+            Self::DuplicateId(id) => write!(f, "The id {id} is used more than once"),
+            Self::MissingRange { card_id } => write!(
+                f,
+                "Card with id {card_id} has an action that can target others but without a range \
+                 specified"
+            ),
+            Self::UnknownCard(card_id) => write!(f, "Unknown card {card_id}"),
+            Self::UnknownArchetype(id) => write!(f, "Unknown archetype {id}"),
+            Self::ArchetypeCycle(cycle) => write!(f, "Archetype cycle: {}", cycle.join(" -> ")),
+            Self::MissingField { name, field } => write!(
+                f,
+                "{name} has no {field} of its own or from an archetype it extends"
+            ),
+            Self::UnknownCharacter(id) => write!(f, "Unknown team member {id}"),
+            Self::EmptyTeam { team } => write!(f, "{team} has no members"),
+            Self::OutOfBounds { x, y } => write!(f, "{x}, {y} is outside the board"),
+            Self::Overlap { x, y } => write!(f, "Multiple entries found at {x}, {y}"),
+            Self::MissingImage { image } => write!(f, "Image {image} doesn't exist"),
+            Self::MaxHealthBelowBase {
+                name,
+                base_health,
+                max_health,
+            } => write!(
+                f,
+                "{name} has a max health of {max_health}, below its base health of {base_health}"
+            ),
+            Self::UnusedCard(card_id) => write!(f, "Card {card_id} is never used"),
+            Self::ServerRequired => {
+                f.write_str("Battles with human players must be hosted by a web server")
+            }
+            Self::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Format version {found} is newer than the latest supported version, {supported}"
+            ),
+            Self::ImportUnreadable { path, reason } => {
+                write!(f, "Unable to import {path}: {reason}")
+            }
+            Self::ImportCycle(cycle) => write!(f, "Import cycle: {}", cycle.join(" -> ")),
+            Self::InImport { path, diagnostic } => {
+                write!(
+                    f,
+                    "{path}:{}:{}: {}",
+                    diagnostic.line, diagnostic.column, diagnostic.error
+                )?;
+                // Nested imports are already located by the file they are in
+                match diagnostic.error {
+                    Self::InImport { .. } => Ok(()),
+                    _ => write!(f, " ({})", diagnostic.path),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(&'static str),
+    Index(usize),
+}
+
+/// Where a value is within a document, such as `$.teams[0].members[1].cards[2]`. Every format is
+/// described as though it were JSON.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JsonPath(pub Vec<PathSegment>);
+
+impl JsonPath {
+    pub fn key(&self, key: &'static str) -> Self {
+        self.with(PathSegment::Key(key))
+    }
+
+    pub fn index(&self, index: usize) -> Self {
+        self.with(PathSegment::Index(index))
+    }
+
+    fn with(&self, segment: PathSegment) -> Self {
+        let mut path = self.clone();
+        path.0.push(segment);
+        path
+    }
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for segment in &self.0 {
+            match segment {
+                PathSegment::Key(key) => write!(f, ".{key}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A problem found in a battle file, along with where it is in the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub error: BattleFileError,
+    pub path: JsonPath,
+    /// Starts from 1
+    pub line: usize,
+    /// Starts from 1, counted in characters
+    pub column: usize,
+}
+
+impl Diagnostic {
+    /// The problem serde found when a file isn't valid JSON or isn't shaped like what was expected
+    pub fn syntax(err: &serde_json::Error) -> Self {
+        // The location is already part of the diagnostic
+        let message = err.to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message);
+        Self {
+            error: BattleFileError::Syntax(message.into()),
+            path: JsonPath::default(),
+            // Errors from values rather than text have no location
+            line: err.line().max(1),
+            column: err.column().max(1),
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.error.severity()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {} ({})",
+            self.line,
+            self.column,
+            self.severity(),
+            self.error,
+            self.path
+        )
+    }
+}
+
+/// Every problem found in a battle file, in the order they appear in it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    /// Locates each problem found at a path within `data`, the file they were found in
+    pub fn locate(
+        data: &str,
+        format: BattleFormat,
+        problems: Vec<(BattleFileError, JsonPath)>,
+    ) -> Self {
+        let mut diagnostics: Vec<Diagnostic> = problems
+            .into_iter()
+            .map(|(error, path)| {
+                let offset = match format {
+                    BattleFormat::Json | BattleFormat::Ron => find_value(data, &path),
+                    BattleFormat::Toml => find_toml_value(data, &path),
+                };
+                let (line, column) = line_and_column(data, offset);
+                Diagnostic {
+                    error,
+                    path,
+                    line,
+                    column,
+                }
+            })
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+        Self(diagnostics)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.0
+            .iter()
+            .any(|diagnostic| diagnostic.severity() == Severity::Error)
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (index, diagnostic) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+pub(crate) fn line_and_column(data: &str, offset: usize) -> (usize, usize) {
+    let before = &data[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Finds the byte offset of the value at `path` in `data`, which must be valid JSON or RON. If part
+/// of the path is missing, such as an optional field that was left out, the closest value that does
+/// exist is found instead.
+fn find_value(data: &str, path: &JsonPath) -> usize {
+    let bytes = data.as_bytes();
+    let mut offset = skip_whitespace(bytes, 0);
+    for segment in &path.0 {
+        match find_child(bytes, offset, segment) {
+            Some(child) => offset = child,
+            None => break,
+        }
+    }
+    offset
+}
+
+fn find_child(bytes: &[u8], offset: usize, segment: &PathSegment) -> Option<usize> {
+    let offset = skip_ron_wrappers(bytes, offset);
+    // RON writes structs and tuples in parentheses
+    let close = match (segment, bytes.get(offset)?) {
+        (PathSegment::Key(_), b'{') => b'}',
+        (PathSegment::Index(_), b'[') => b']',
+        (_, b'(') => b')',
+        _ => return None,
+    };
+    let mut offset = skip_whitespace(bytes, offset + 1);
+    let mut index = 0;
+    while bytes.get(offset) != Some(&close) {
+        let is_match = match segment {
+            PathSegment::Key(key) => {
+                let key_end = skip_value(bytes, offset)?;
+                let found = &bytes[offset..key_end];
+                // RON struct fields aren't quoted
+                let is_match = found == key.as_bytes()
+                    || serde_json::from_slice::<String>(found).is_ok_and(|found| found == *key);
+                // Skips past the colon
+                offset = skip_whitespace(bytes, skip_whitespace(bytes, key_end) + 1);
+                is_match
+            }
+            PathSegment::Index(target) => index == *target,
+        };
+        if is_match {
+            return Some(offset);
+        }
+        offset = skip_whitespace(bytes, skip_value(bytes, offset)?);
+        if bytes.get(offset) == Some(&b',') {
+            offset = skip_whitespace(bytes, offset + 1);
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Skips whitespace along with any comments, which only RON has
+fn skip_whitespace(bytes: &[u8], mut offset: usize) -> usize {
+    loop {
+        match (bytes.get(offset), bytes.get(offset + 1)) {
+            (Some(byte), _) if byte.is_ascii_whitespace() => offset += 1,
+            (Some(b'/'), Some(b'/')) => {
+                while bytes.get(offset).is_some_and(|byte| *byte != b'\n') {
+                    offset += 1;
+                }
+            }
+            (Some(b'/'), Some(b'*')) => {
+                offset += 2;
+                while bytes.get(offset).is_some() && !bytes[offset..].starts_with(b"*/") {
+                    offset += 1;
+                }
+                offset += 2;
+            }
+            _ => return offset.min(bytes.len()),
+        }
+    }
+}
+
+/// Skips past the `Some` an optional RON value may be wrapped in, or to the parentheses of a
+/// struct written with its name
+fn skip_ron_wrappers(bytes: &[u8], offset: usize) -> usize {
+    let name_end = offset
+        + bytes[offset..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_')
+            .count();
+    let open = skip_whitespace(bytes, name_end);
+    if name_end == offset || bytes.get(open) != Some(&b'(') {
+        offset
+    } else if &bytes[offset..name_end] == b"Some" {
+        skip_ron_wrappers(bytes, skip_whitespace(bytes, open + 1))
+    } else {
+        open
+    }
+}
+
+/// Finds the end of the value starting at `offset`
+fn skip_value(bytes: &[u8], offset: usize) -> Option<usize> {
+    match bytes.get(offset)? {
+        b'"' => {
+            let mut offset = offset + 1;
+            loop {
+                match bytes.get(offset)? {
+                    b'\\' => offset += 2,
+                    b'"' => return Some(offset + 1),
+                    _ => offset += 1,
+                }
+            }
+        }
+        b'r' if matches!(bytes.get(offset + 1), Some(b'"' | b'#')) => {
+            // RON raw strings end with as many #s as they start with
+            let hashes = bytes[offset + 1..]
+                .iter()
+                .take_while(|byte| **byte == b'#')
+                .count();
+            let mut end = vec![b'"'];
+            end.extend(vec![b'#'; hashes]);
+            let start = offset + hashes + 2;
+            let length = bytes[start..]
+                .windows(end.len())
+                .position(|window| window == end)?;
+            Some(start + length + end.len())
+        }
+        open @ (b'{' | b'[' | b'(') => {
+            let close = match open {
+                b'{' => b'}',
+                b'[' => b']',
+                _ => b')',
+            };
+            let mut offset = skip_whitespace(bytes, offset + 1);
+            while *bytes.get(offset)? != close {
+                offset = skip_whitespace(bytes, skip_value(bytes, offset)?);
+                if matches!(bytes.get(offset)?, b',' | b':') {
+                    offset = skip_whitespace(bytes, offset + 1);
+                }
+            }
+            Some(offset + 1)
+        }
+        _ => {
+            let mut offset = offset;
+            while bytes.get(offset).is_some_and(|byte| {
+                !matches!(byte, b',' | b'}' | b']' | b')' | b'(' | b':')
+                    && !byte.is_ascii_whitespace()
+            }) {
+                offset += 1;
+            }
+            // Named RON values, like `Some(1)`, are followed by their contents
+            let open = skip_whitespace(bytes, offset);
+            match bytes.get(open) {
+                Some(b'(') => skip_value(bytes, open),
+                _ => Some(offset),
+            }
+        }
+    }
+}
+
+/// Finds the byte offset of the value at `path` in `data`, which must be valid TOML. Like
+/// `find_value`, anything missing is located by the closest value that does exist, as are tables
+/// that only exist through dotted keys.
+fn find_toml_value(data: &str, path: &JsonPath) -> usize {
+    let Ok(document) = toml_edit::ImDocument::parse(data) else {
+        return 0;
+    };
+    let mut item = document.as_item();
+    let mut offset = 0;
+    for segment in &path.0 {
+        let child = match segment {
+            PathSegment::Key(key) => item.get(key),
+            PathSegment::Index(index) => item.get(index),
+        };
+        let Some(child) = child else {
+            break;
+        };
+        item = child;
+        if let Some(span) = item.span() {
+            offset = span.start;
+        }
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_value, line_and_column, BattleFileError, BattleFormat, Diagnostics, JsonPath,
+    };
+
+    #[test]
+    fn test_locate() {
+        let data = r#"{
+    "title": "A \"quoted\" title",
+    "teams": [
+        { "name": "Team A", "members": [] },
+        {
+            "name": "Team B",
+            "members": [{ "name": "Ünïcode", "cards": ["punch", "kick"] }]
+        }
+    ]
+}"#;
+        let locate = |path: &JsonPath| line_and_column(data, find_value(data, path));
+        let teams = JsonPath::default().key("teams");
+        let member = teams.index(1).key("members").index(0);
+
+        assert_eq!(locate(&JsonPath::default()), (1, 1));
+        assert_eq!(locate(&JsonPath::default().key("title")), (2, 14));
+        assert_eq!(locate(&teams.index(0).key("members")), (4, 40));
+        assert_eq!(locate(&member.key("cards").index(1)), (7, 65));
+        assert_eq!(
+            locate(&member.key("max_health")),
+            (7, 25),
+            "Missing values point to the closest one that exists"
+        );
+
+        let diagnostics = Diagnostics::locate(
+            data,
+            BattleFormat::Json,
+            vec![
+                (
+                    BattleFileError::UnknownCard("kick".into()),
+                    member.key("cards").index(1),
+                ),
+                (
+                    BattleFileError::EmptyTeam {
+                        team: "Team A".into(),
+                    },
+                    teams.index(0).key("members"),
+                ),
+            ],
+        );
+        assert_eq!(
+            diagnostics.to_string(),
+            "4:40: error: Team A has no members ($.teams[0].members)\n\
+             7:65: error: Unknown card kick ($.teams[1].members[0].cards[1])"
+        );
+    }
+}
@@ -71,7 +71,10 @@ impl Actor for DumbActor {
 
             for (team_id, actor) in &battle.actors {
                 let opponent = &battle.characters[actor.get_character_id()];
-                if &my_team != team_id && !opponent.is_dead() {
+                if &my_team != team_id
+                    && !opponent.is_dead()
+                    && battle.is_visible_to(my_team, &opponent.id)
+                {
                     if let Some(distance) = battle.board.distance(
                         BoardItem::Character(character.id),
                         BoardItem::Character(opponent.id),
@@ -97,5 +100,5 @@ impl Actor for DumbActor {
         Ok(Action::Pass)
     }
 
-    async fn on_game_over(&self, _battle: &Battle) {}
+    async fn on_game_over(&self, _battle: &Battle, _outcome: &BattleOutcome) {}
 }
@@ -1,17 +1,18 @@
 use std::collections::VecDeque;
 
 use serde::Serialize;
+use ts_rs::TS;
 
 pub type GridDimension = usize;
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, TS)]
 pub struct Grid<T> {
     members: Vec<Vec<Option<T>>>,
     width: GridDimension,
     height: GridDimension,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, TS)]
 pub struct GridLocation {
     pub x: GridDimension,
     pub y: GridDimension,
@@ -111,8 +112,8 @@ impl<T> Grid<T> {
         F: Fn(&T) -> bool,
     {
         let mut ret = vec![];
-        for x in location.x - range..=location.x + range {
-            for y in location.y - range..=location.y + range {
+        for x in location.x.saturating_sub(range)..=location.x + range {
+            for y in location.y.saturating_sub(range)..=location.y + range {
                 if location.distance(&GridLocation { x, y }) > range {
                     continue;
                 }
@@ -126,6 +127,93 @@ impl<T> Grid<T> {
         ret
     }
 
+    /// Finds the unset location closest to `location`, preferring earlier rows and columns on ties
+    pub fn find_nearest_empty(&self, location: &GridLocation) -> Option<GridLocation> {
+        let mut nearest: Option<GridLocation> = None;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.is_set(x, y) {
+                    continue;
+                }
+                let candidate = GridLocation { x, y };
+                if nearest.as_ref().map_or(true, |n| {
+                    candidate.distance(location) < n.distance(location)
+                }) {
+                    nearest = Some(candidate);
+                }
+            }
+        }
+        nearest
+    }
+
+    /// Checks whether a straight line between two locations is unobstructed. Neither end of the line
+    /// blocks sight.
+    pub fn has_line_of_sight<F>(
+        &self,
+        from: &GridLocation,
+        to: &GridLocation,
+        blocks_sight: F,
+    ) -> bool
+    where
+        F: Fn(&T) -> bool,
+    {
+        // Bresenham's line algorithm
+        let (to_x, to_y) = (to.x as i64, to.y as i64);
+        let (mut x, mut y) = (from.x as i64, from.y as i64);
+        let dx = (to_x - x).abs();
+        let dy = -(to_y - y).abs();
+        let step_x = if x < to_x { 1 } else { -1 };
+        let step_y = if y < to_y { 1 } else { -1 };
+        let mut error = dx + dy;
+        if dx == 0 && dy == 0 {
+            return true;
+        }
+        loop {
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+            if x == to_x && y == to_y {
+                return true;
+            }
+            if self
+                .get(x as GridDimension, y as GridDimension)
+                .is_some_and(&blocks_sight)
+            {
+                return false;
+            }
+        }
+    }
+
+    /// Finds every location within `range` of `location` that has a line of sight to it
+    pub fn find_visible<F>(
+        &self,
+        location: &GridLocation,
+        range: GridDimension,
+        blocks_sight: F,
+    ) -> Vec<GridLocation>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut ret = vec![];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let candidate = GridLocation { x, y };
+                if location.distance(&candidate) <= range
+                    && self.has_line_of_sight(location, &candidate, &blocks_sight)
+                {
+                    ret.push(candidate);
+                }
+            }
+        }
+        ret
+    }
+
     pub fn get(&self, x: GridDimension, y: GridDimension) -> Option<&T> {
         if self.is_valid(x, y) {
             self.members[y][x].as_ref()
@@ -302,6 +390,67 @@ mod tests {
             ],
             "Range of 1 should only include directly above and to the side"
         );
+
+        assert_eq!(
+            grid.find_in_range(GridLocation { x: 0, y: 0 }, 1, |_value| true),
+            vec![
+                GridLocation { x: 0, y: 0 },
+                GridLocation { x: 0, y: 1 },
+                GridLocation { x: 1, y: 0 },
+            ],
+            "Ranges are clipped at the edge of the grid"
+        );
+    }
+
+    #[test]
+    pub fn test_find_nearest_empty() {
+        let mut grid = Grid::new(3, 3);
+        assert_eq!(
+            grid.find_nearest_empty(&GridLocation { x: 1, y: 1 }),
+            Some(GridLocation { x: 1, y: 1 }),
+            "An empty location is its own nearest"
+        );
+
+        grid.set(1, 1, 'a');
+        grid.set(1, 0, 'b');
+        assert_eq!(
+            grid.find_nearest_empty(&GridLocation { x: 1, y: 1 }),
+            Some(GridLocation { x: 0, y: 1 }),
+            "Ties resolve to the earliest row"
+        );
+
+        for x in 0..3 {
+            for y in 0..3 {
+                grid.set(x, y, 'z');
+            }
+        }
+        assert_eq!(grid.find_nearest_empty(&GridLocation { x: 1, y: 1 }), None);
+    }
+
+    #[test]
+    pub fn test_line_of_sight() {
+        let mut grid = Grid::new(5, 3);
+        grid.set(2, 1, 'w');
+        let is_wall = |entry: &char| *entry == 'w';
+        let from = GridLocation { x: 0, y: 1 };
+
+        assert!(grid.has_line_of_sight(&from, &from, is_wall));
+        assert!(grid.has_line_of_sight(&from, &GridLocation { x: 2, y: 1 }, is_wall));
+        assert!(!grid.has_line_of_sight(&from, &GridLocation { x: 4, y: 1 }, is_wall));
+        assert!(grid.has_line_of_sight(&from, &GridLocation { x: 4, y: 0 }, is_wall));
+
+        assert_eq!(
+            grid.find_visible(&GridLocation { x: 4, y: 1 }, 2, is_wall),
+            vec![
+                GridLocation { x: 3, y: 0 },
+                GridLocation { x: 4, y: 0 },
+                GridLocation { x: 2, y: 1 },
+                GridLocation { x: 3, y: 1 },
+                GridLocation { x: 4, y: 1 },
+                GridLocation { x: 3, y: 2 },
+                GridLocation { x: 4, y: 2 },
+            ]
+        );
     }
 
     #[test]
@@ -3,35 +3,51 @@
 pub mod action;
 pub mod actor;
 pub mod battle;
+pub mod battle_delta;
 mod battle_deserialize;
 pub mod battle_file;
+pub mod battle_format;
 pub mod battle_history;
+mod battle_imports;
 pub mod battle_menu;
+pub mod battle_migrations;
+pub mod battle_view;
 pub mod board;
 pub mod card;
 pub mod character;
+pub mod diagnostics;
 pub mod dumb_actor;
 pub mod grid;
 pub mod menu;
+pub mod observer;
 pub mod random_provider;
+pub mod rules;
 pub mod template;
 pub mod terminal_actor;
 pub mod terminal_ui;
+pub mod typescript;
 pub mod web_actor;
 pub mod wrapped_type;
 
 pub use action::*;
 pub use actor::*;
 pub use battle::*;
+pub use battle_delta::*;
+pub use battle_format::*;
 pub use battle_history::*;
 pub use battle_menu::*;
+pub use battle_view::*;
 pub use board::*;
 pub use card::*;
 pub use character::*;
+pub use diagnostics::*;
 pub use dumb_actor::*;
 pub use grid::*;
 pub use menu::*;
+pub use observer::*;
 pub use random_provider::*;
+pub use rules::*;
 pub use template::*;
 pub use terminal_actor::*;
 pub use terminal_ui::*;
+pub use typescript::*;
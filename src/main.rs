@@ -1,27 +1,172 @@
+use clap::{Args, Parser, Subcommand};
 use cpd::*;
-use std::{env, fs, ops::Deref, path::PathBuf, process::ExitCode};
+use std::{fs, path::Path, process::ExitCode};
+#[cfg(not(feature = "terminal_ui"))]
+use {
+    cpd::web_actor::{ServerConfig, WebActorServer},
+    futures::future::join_all,
+};
 #[cfg(feature = "terminal_ui")]
 use {std::io, termion::raw::IntoRawMode};
 
-#[actix_web::main]
-async fn main() -> Result<(), ExitCode> {
-    let args: Vec<String> = env::args().collect();
-    let file = args
-        .get(1)
-        .map(Deref::deref)
-        .unwrap_or("sample-battle.json");
-    let file_path = format!("data/{file}");
-
-    let battle_file = fs::read_to_string(&file_path)
-        .unwrap_or_else(|_| panic!("Unable to open file: {file_path}"));
+const DEFAULT_BATTLE_FILE: &str = "sample-battle.json";
+
+#[derive(Parser)]
+#[command(about)]
+struct Cli {
+    #[command(flatten)]
+    options: Options,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Battle file to play when no command is given, the same as `play`
+    #[arg(default_value = DEFAULT_BATTLE_FILE)]
+    file: String,
+}
+
+#[derive(Args)]
+struct Options {
+    /// Address the web server listens on
+    #[arg(long, env = "CPD_HOST", default_value = "0.0.0.0", global = true)]
+    host: String,
+
+    /// Port the web server listens on. 0 picks any free port.
+    #[arg(long, env = "CPD_PORT", default_value_t = 8000, global = true)]
+    port: u16,
+
+    /// Directory battle files and their assets are loaded from
+    #[arg(long, env = "CPD_DATA_DIR", default_value = "data", global = true)]
+    data_dir: String,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Plays a single battle, which is what happens when no command is given
+    Play {
+        /// Battle file to play, relative to the data directory. It may be written in JSON, TOML or
+        /// RON, chosen by its extension.
+        #[arg(default_value = DEFAULT_BATTLE_FILE)]
+        file: String,
+    },
+    /// Hosts several battles at once on a single web server
+    Serve {
+        /// Battle files to host, relative to the data directory
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Prints the JSON Schema battle files are written against
+    Schema,
+    /// Reports every problem with battle files without playing them
+    Check {
+        /// Battle files to check, relative to the data directory
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Rewrites battle files in the latest version of the format
+    Upgrade {
+        /// Battle files to upgrade, relative to the data directory
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Rewrites a battle file or library in another format, chosen by the extension of the
+    /// output. Comments aren't kept.
+    Convert {
+        /// File to convert, relative to the data directory
+        input: String,
+        /// Where to write the converted file, relative to the data directory
+        output: String,
+    },
+}
+
+fn read_battle_file(options: &Options, file: &str) -> String {
+    let file_path = Path::new(&options.data_dir).join(file);
+    fs::read_to_string(&file_path)
+        .unwrap_or_else(|_| panic!("Unable to open file: {}", file_path.display()))
+}
+
+fn write_battle_file(options: &Options, file: &str, data: String) {
+    let file_path = Path::new(&options.data_dir).join(file);
+    fs::write(&file_path, data)
+        .unwrap_or_else(|_| panic!("Unable to write file: {}", file_path.display()));
+}
+
+fn battle_format(file: &str) -> BattleFormat {
+    BattleFormat::from_path(Path::new(file))
+        .unwrap_or_else(|| panic!("Battle files must end in .json, .toml or .ron: {file}"))
+}
+
+/// Prints each problem found in a battle file, returning the failure to exit with
+fn report_problems(file: &str, diagnostics: &Diagnostics) -> ExitCode {
+    for diagnostic in &diagnostics.0 {
+        eprintln!("{file}:{diagnostic}");
+    }
+    ExitCode::FAILURE
+}
+
+/// Reports every problem found in the files, failing if any of them are errors
+fn check(options: &Options, files: &[String]) -> Result<(), ExitCode> {
+    let mut has_errors = false;
+    for file in files {
+        let diagnostics = battle_file::Battle::check(
+            &read_battle_file(options, file),
+            battle_format(file),
+            Some(Path::new(&options.data_dir)),
+        );
+        report_problems(file, &diagnostics);
+        has_errors |= diagnostics.has_errors();
+    }
+    if has_errors {
+        Err(ExitCode::FAILURE)
+    } else {
+        Ok(())
+    }
+}
+
+/// Rewrites each file that isn't in the latest version of the format, failing if any can't be
+fn upgrade(options: &Options, files: &[String]) -> Result<(), ExitCode> {
+    let mut result = Ok(());
+    for file in files {
+        match battle_migrations::upgrade(&read_battle_file(options, file), battle_format(file)) {
+            Ok(Some(upgraded)) => {
+                write_battle_file(options, file, upgraded);
+                println!("Upgraded {file}");
+            }
+            Ok(None) => println!("{file} is already up to date"),
+            Err(diagnostics) => result = Err(report_problems(file, &diagnostics)),
+        }
+    }
+    result
+}
+
+/// Writes a file out in the format of `output`, failing if the input can't be read or written in
+/// that format
+fn convert(options: &Options, input: &str, output: &str) -> Result<(), ExitCode> {
+    let value = battle_format(input)
+        .parse(&read_battle_file(options, input))
+        .map_err(|diagnostic| report_problems(input, &Diagnostics(vec![diagnostic])))?;
+    let converted = battle_format(output)
+        .serialize(&value)
+        .map_err(|diagnostic| report_problems(input, &Diagnostics(vec![diagnostic])))?;
+    write_battle_file(options, output, converted);
+    println!("Converted {input} to {output}");
+    Ok(())
+}
+
+#[cfg(feature = "terminal_ui")]
+async fn run(options: &Options, files: Vec<String>) -> Result<(), ExitCode> {
+    let [file] = &files[..] else {
+        eprintln!("The terminal UI can only run one battle at a time");
+        return Err(ExitCode::FAILURE);
+    };
     let mut battle = Battle::deserialize(
-        &battle_file,
-        Some(PathBuf::from("data")),
+        &read_battle_file(options, file),
+        battle_format(file),
+        Some(options.data_dir.clone().into()),
         Box::<DefaultRandomProvider>::default(),
     )
     .await
-    .unwrap();
-    #[cfg(feature = "terminal_ui")]
+    .map_err(|diagnostics| report_problems(file, &diagnostics))?;
     let (_out, _err) = (
         io::stdout().into_raw_mode().unwrap(),
         io::stderr().into_raw_mode().unwrap(),
@@ -31,3 +176,103 @@ async fn main() -> Result<(), ExitCode> {
 
     Ok(())
 }
+
+/// Hosts every battle on a single web server, each under a session named after its file
+#[cfg(not(feature = "terminal_ui"))]
+async fn run(options: &Options, files: Vec<String>) -> Result<(), ExitCode> {
+    let server = WebActorServer::new(&ServerConfig {
+        host: options.host.clone(),
+        port: options.port,
+        asset_directory: Some(options.data_dir.clone().into()),
+    })
+    .unwrap_or_else(|err| panic!("Unable to start server: {err}"));
+
+    let mut battles = vec![];
+    for file in &files {
+        let session_id = Path::new(file)
+            .file_stem()
+            .map_or(file.into(), |stem| stem.to_string_lossy());
+        battles.push(
+            Battle::deserialize_in_session(
+                &read_battle_file(options, file),
+                battle_format(file),
+                Some(options.data_dir.clone().into()),
+                Box::<DefaultRandomProvider>::default(),
+                &server,
+                &session_id,
+            )
+            .await
+            .map_err(|diagnostics| report_problems(file, &diagnostics))?,
+        );
+    }
+
+    // Battles aren't Send so they all share this thread, taking turns while waiting on players
+    let results =
+        join_all(battles.into_iter().map(|mut battle| {
+            actix_web::rt::spawn(async move { battle.run_to_completion().await })
+        }))
+        .await;
+    println!("Game over");
+
+    for result in results {
+        result.expect("Battle panicked")?;
+    }
+    Ok(())
+}
+
+#[actix_web::main]
+async fn main() -> Result<(), ExitCode> {
+    let cli = Cli::parse();
+    let files = match cli.command {
+        Some(Command::Play { file }) => vec![file],
+        Some(Command::Serve { files }) => files,
+        Some(Command::Schema) => {
+            println!("{}", battle_file::Battle::json_schema());
+            return Ok(());
+        }
+        Some(Command::Check { files }) => return check(&cli.options, &files),
+        Some(Command::Upgrade { files }) => return upgrade(&cli.options, &files),
+        Some(Command::Convert { input, output }) => return convert(&cli.options, &input, &output),
+        None => vec![cli.file],
+    };
+
+    run(&cli.options, files).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Command};
+    use clap::Parser;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(["cpd"].iter().chain(args)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_options_anywhere() {
+        let cli = parse(&["--port", "0", "serve", "a.json"]);
+        assert_eq!(cli.options.port, 0);
+        assert!(matches!(cli.command, Some(Command::Serve { files }) if files == ["a.json"]));
+
+        let cli = parse(&["check", "--port", "1", "mines.json"]);
+        assert_eq!(cli.options.port, 1);
+        assert!(matches!(cli.command, Some(Command::Check { files }) if files == ["mines.json"]));
+
+        let cli = parse(&["--data-dir", "battles", "schema"]);
+        assert_eq!(cli.options.data_dir, "battles");
+        assert!(matches!(cli.command, Some(Command::Schema)));
+
+        let cli = parse(&["--port", "1", "mines.json"]);
+        assert_eq!(cli.options.port, 1);
+        assert!(cli.command.is_none());
+        assert_eq!(cli.file, "mines.json");
+
+        let cli = parse(&["play", "mines.json", "--host", "127.0.0.1"]);
+        assert_eq!(cli.options.host, "127.0.0.1");
+        assert!(matches!(cli.command, Some(Command::Play { file }) if file == "mines.json"));
+
+        let cli = parse(&[]);
+        assert!(cli.command.is_none());
+        assert_eq!(cli.file, "sample-battle.json");
+    }
+}
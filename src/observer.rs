@@ -0,0 +1,18 @@
+use crate::*;
+
+use async_trait::async_trait;
+
+/// What caused a battle to change
+#[derive(Debug)]
+pub enum BattleUpdate {
+    TurnStarted(CharacterId),
+    ActionTaken(CharacterId, Action),
+    GameOver,
+}
+
+/// Watches a battle without taking part in it
+#[async_trait]
+pub trait BattleObserver: Sync {
+    /// Called whenever the state of the battle changes
+    async fn on_update(&self, battle: &Battle, update: &BattleUpdate);
+}
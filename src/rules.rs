@@ -0,0 +1,91 @@
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::{CharacterId, GridLocation, TeamId, U64Range};
+
+/// What remains on the board once a character is defeated
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
+pub enum DeadBody {
+    /// The character is removed from the board entirely
+    Remove,
+    /// The character stays where they fell and blocks movement
+    Corpse,
+    /// The character leaves passable remains that are trampled when walked over
+    Remains,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct DeathRule {
+    pub body: DeadBody,
+    /// Whether damage with an area still hits defeated characters. Healing never affects them.
+    pub area_effects_hit_dead: bool,
+}
+
+impl Default for DeathRule {
+    fn default() -> Self {
+        Self {
+            body: DeadBody::Corpse,
+            area_effects_hit_dead: true,
+        }
+    }
+}
+
+/// A goal a team must achieve to win the battle
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
+pub enum Objective {
+    /// Every opposing team is defeated
+    EliminateAll,
+    /// The given character is defeated
+    Defeat(CharacterId),
+    /// At least one team member is alive at the end of the given round
+    Survive(u16),
+    /// A living team member stands on the given location
+    ReachTile(GridLocation),
+    /// The team is defeated if the given character falls
+    Protect(CharacterId),
+}
+
+/// The team that won the battle and the objective that decided it
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
+pub struct Victory {
+    pub team: TeamId,
+    pub objective: Objective,
+}
+
+/// Why a battle ended without a winner
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
+pub enum DrawReason {
+    /// Every team was defeated at the same time
+    MutualDefeat,
+    /// The battle reached its maximum number of rounds
+    RoundLimit(u16),
+    /// No character's health changed for the given number of rounds
+    Stalemate(u16),
+    /// Walls keep every team from ever hurting another, so no one can win
+    Unreachable,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
+pub enum BattleOutcome {
+    Victory(Victory),
+    Draw(DrawReason),
+}
+
+/// How the characters taking turns in a round are ordered
+#[derive(Debug, PartialEq, Clone, Serialize, TS)]
+pub enum TurnOrder {
+    /// Each team in order, with members acting in the order they are listed
+    Sequential,
+    /// Highest speed plus a random roll acts first, rolled each round
+    Initiative(U64Range),
+    /// Teams take turns one member at a time, rotating which team leads each round
+    TeamAlternating,
+    /// Characters gain their speed in action points every tick and act whenever they have enough,
+    /// so faster characters may act several times a round
+    Timeline,
+}
+
+/// The number of ticks in a round of a [`TurnOrder::Timeline`]
+pub const TIMELINE_TICKS_PER_ROUND: u64 = 10;
+/// The action points a character spends to take a turn on a [`TurnOrder::Timeline`]
+pub const TIMELINE_TURN_COST: u64 = 100;
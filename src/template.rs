@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 
 use serde::Serialize;
+use ts_rs::TS;
 
 pub trait TemplateRenderer<TypeId> {
     fn render_text(&self, string: &str) -> String {
@@ -10,7 +11,7 @@ pub trait TemplateRenderer<TypeId> {
     fn render(&self, type_id: &TypeId, string: &str) -> String;
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, TS)]
 pub enum TemplateEntry<TypeId> {
     Text(String),
     Typed(TypeId, String),
@@ -33,9 +33,17 @@ impl TerminalActor {
 
     fn get_battle_status(&self, battle: &Battle) -> Vec<TerminalBlock> {
         let mut blocks = vec![];
+        let my_team = battle.get_team_for_actor(self);
 
-        for entry in &battle.history {
-            blocks.push(TerminalBlock::new(battle_history_to_terminal_string(entry)));
+        // Matches what the web client is sent, leaving out what enemies did while unseen
+        for entry in battle
+            .history
+            .iter()
+            .filter(|entry| my_team.map_or(true, |my_team| !entry.hidden_from.contains(&my_team)))
+        {
+            blocks.push(TerminalBlock::new(battle_history_to_terminal_string(
+                &entry.text,
+            )));
         }
 
         if !battle.history.is_empty() {
@@ -50,6 +58,13 @@ impl TerminalActor {
                     continue;
                 }
                 let character = battle.get_character(actor.as_ref());
+                if my_team.is_some_and(|my_team| !battle.is_visible_to(my_team, &character.id)) {
+                    blocks.push(TerminalBlock::new(format!(
+                        "- {} ({}). Unseen",
+                        character.name, character.id
+                    )));
+                    continue;
+                }
                 blocks.push(TerminalBlock::new(if character.is_dead() {
                     format!("- {} ({}). Dead 💀", character.name, character.id)
                 } else {
@@ -62,23 +77,6 @@ impl TerminalActor {
         }
         blocks
     }
-
-    fn get_enemies(&self, battle: &Battle) -> Vec<CharacterId> {
-        let mut enemies = vec![];
-        let my_team = battle.get_team_for_actor(self);
-        for team in &battle.teams {
-            for (team_id, actor) in &battle.actors {
-                if team_id != &team.id {
-                    continue;
-                }
-                let character = battle.get_character(actor.as_ref());
-                if Some(*team_id) != my_team && !character.is_dead() {
-                    enemies.push(character.id);
-                }
-            }
-        }
-        enemies
-    }
 }
 
 struct TerminalTemplateRenderer {}
@@ -118,6 +116,7 @@ impl Actor for TerminalActor {
     async fn act(&self, battle: &Battle) -> ActionResult {
         let mut blocks = self.get_battle_status(battle);
         blocks.push(TerminalBlock::default());
+        let my_team = battle.get_team_for_actor(self);
 
         let menu = BattleMenu::new(vec![
             Rc::new(ActionsMenu {
@@ -126,9 +125,11 @@ impl Actor for TerminalActor {
                 targets: battle
                     .characters
                     .iter()
-                    .filter_map(
-                        |(id, character)| if character.is_dead() { None } else { Some(*id) },
-                    )
+                    .filter(|(id, character)| {
+                        !character.is_dead()
+                            && my_team.map_or(true, |my_team| battle.is_visible_to(my_team, id))
+                    })
+                    .map(|(id, _)| *id)
                     .collect(),
             }),
             Rc::new(PassMenuItem {}),
@@ -141,16 +142,103 @@ impl Actor for TerminalActor {
         }
     }
 
-    async fn on_game_over(&self, battle: &Battle) {
+    async fn on_game_over(&self, battle: &Battle, outcome: &BattleOutcome) {
         let mut blocks = self.get_battle_status(battle);
 
-        blocks.push(TerminalBlock::new(if self.get_enemies(battle).is_empty() {
-            "You win!"
-        } else {
-            "You lose!"
+        blocks.push(TerminalBlock::new(match outcome {
+            BattleOutcome::Victory(victory)
+                if battle.get_team_for_actor(self) == Some(victory.team) =>
+            {
+                "You win!"
+            }
+            BattleOutcome::Victory(_) => "You lose!",
+            BattleOutcome::Draw(_) => "It's a draw!",
         }));
+        blocks.push(TerminalBlock::new(battle.describe_outcome(outcome)));
         blocks.push(TerminalBlock::default());
 
         let _ = TerminalUi::draw(&blocks);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TerminalActor;
+    use crate::{
+        battle_markup, Battle, BattleFormat, CharacterId, DefaultRandomProvider, Diagnostics,
+        HistoryEntry, TeamId,
+    };
+
+    #[tokio::test]
+    async fn test_battle_status_hides_unseen_history() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 4, "height": 1 },
+            "walls": [[1, 0]],
+            "cards": [],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [0, 0],
+                            "vision": 3
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [3, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+        )
+        .await?;
+        battle.history = vec![
+            HistoryEntry::public(battle_markup!["--- Round 1"]),
+            HistoryEntry {
+                text: battle_markup![@id(&"Member B"), " took no action"],
+                hidden_from: vec![TeamId::new(0)],
+            },
+        ];
+
+        let status = |character_id: usize| {
+            TerminalActor {
+                character_id: CharacterId::new(character_id),
+            }
+            .get_battle_status(&battle)
+            .into_iter()
+            .map(|block| block.contents)
+            .collect::<Vec<String>>()
+        };
+        let status_a = status(0);
+        assert_eq!(status_a[0], "--- Round 1");
+        assert!(
+            !status_a.iter().any(|line| line.contains("took no action")),
+            "{status_a:?}"
+        );
+        assert!(status_a.contains(&"- Member B (1). Unseen".to_string()));
+        assert!(status(1).iter().any(|line| line.contains("took no action")));
+
+        Ok(())
+    }
+}
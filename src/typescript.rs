@@ -0,0 +1,87 @@
+use ts_rs::TS;
+
+use crate::web_actor::BattleState;
+use crate::{
+    battle_file::StoryCardEntry, BattleDelta, BattleOutcome, BattleTextEntry, BattleView,
+    BoardItem, BoardView, Card, CardAction, CardId, CardPickup, CharacterId, CharacterRace,
+    CharacterView, DeadBody, DeathRule, DrawReason, Grid, GridLocation, Health, Objective, Target,
+    Team, TeamId, TemplateEntry, U64Range, Victory,
+};
+
+/// Where the web client imports the generated definitions from, relative to the crate root
+pub const TYPESCRIPT_DEFINITIONS_PATH: &str = "src/web_actor/static/model.ts";
+
+macro_rules! declarations {
+    ($($type:ty),* $(,)?) => {
+        [$(<$type as TS>::decl()),*]
+    };
+}
+
+/// TypeScript definitions for everything the web client is sent about a battle, matching how each
+/// type is serialized
+pub fn typescript_definitions() -> String {
+    let declarations = declarations![
+        BattleState<'static>,
+        BattleDelta,
+        BattleView<'static>,
+        CharacterView<'static>,
+        BoardView,
+        Grid<()>,
+        GridLocation,
+        BoardItem,
+        Team,
+        TeamId,
+        Objective,
+        Victory,
+        DrawReason,
+        BattleOutcome,
+        CharacterId,
+        CharacterRace,
+        Health,
+        Card,
+        CardId,
+        CardAction,
+        Target,
+        U64Range,
+        CardPickup,
+        DeathRule,
+        DeadBody,
+        TemplateEntry<()>,
+        BattleTextEntry,
+        StoryCardEntry,
+    ];
+    let mut definitions = String::from(
+        "// Generated from the Rust types by `UPDATE_TYPESCRIPT=1 cargo test`. Do not edit.\n",
+    );
+    for declaration in declarations {
+        // JSON has a single kind of number, which JavaScript always parses as a number rather
+        // than the bigint ts-rs uses for 64 bit integers
+        let declaration = declaration.replace("bigint", "number");
+        definitions += &format!("\nexport {declaration}\n");
+    }
+    definitions
+}
+
+// Build scripts run before the crate is compiled so they can't see these types. Instead the
+// definitions are checked in, and this test fails whenever they fall behind.
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use super::{typescript_definitions, TYPESCRIPT_DEFINITIONS_PATH};
+
+    #[test]
+    fn test_typescript_definitions_are_current() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(TYPESCRIPT_DEFINITIONS_PATH);
+        let definitions = typescript_definitions();
+        if env::var_os("UPDATE_TYPESCRIPT").is_some() {
+            fs::write(&path, definitions).unwrap();
+            return;
+        }
+        assert!(
+            fs::read_to_string(&path).is_ok_and(|checked_in| checked_in == definitions),
+            "{TYPESCRIPT_DEFINITIONS_PATH} is out of date. Regenerate it with `UPDATE_TYPESCRIPT=1 \
+             cargo test`."
+        );
+    }
+}
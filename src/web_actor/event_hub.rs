@@ -0,0 +1,222 @@
+use std::{collections::VecDeque, sync::Arc};
+use tokio::sync::{
+    mpsc::{channel, error::TrySendError, Receiver, Sender},
+    Mutex,
+};
+
+/// How many events may queue up for a subscriber before it is dropped for falling behind
+const SUBSCRIBER_BUFFER: usize = 10;
+
+/// How many of the most recent events are kept around to replay to reconnecting subscribers
+const REPLAY_BUFFER: usize = 64;
+
+pub type ArcEventHub = Arc<Mutex<EventHub>>;
+
+/// Identifies an event within its hub. Later events always have larger ids.
+pub type EventId = u64;
+
+/// An event for clients, independent of how it is delivered to them
+#[derive(Clone, Debug)]
+pub struct HubEvent {
+    pub id: EventId,
+    pub name: &'static str,
+    /// Serialized JSON payload
+    pub data: String,
+}
+
+/// Fans events out to any number of subscribers. Recent events are buffered so subscribers that
+/// reconnect can catch up on what they missed, otherwise they start over from a snapshot.
+#[derive(Default)]
+pub struct EventHub {
+    subscribers: Vec<Sender<HubEvent>>,
+    recent: VecDeque<HubEvent>,
+    /// The full state as of the latest event
+    snapshot: Option<HubEvent>,
+    next_id: EventId,
+}
+
+impl EventHub {
+    /// Subscribes to future events. New subscribers are sent the snapshot so they start out with
+    /// the current state. Subscribers resuming after `last_event_id` are instead sent every event
+    /// since then, or the snapshot if some of those are no longer buffered.
+    pub fn subscribe(&mut self, last_event_id: Option<EventId>) -> Receiver<HubEvent> {
+        // Ids from before the server restarted may be larger than any sent since
+        let missed: Vec<&HubEvent> = match last_event_id {
+            Some(last_event_id)
+                if last_event_id < self.next_id
+                    && self
+                        .recent
+                        .front()
+                        .is_some_and(|oldest| oldest.id <= last_event_id + 1) =>
+            {
+                self.recent
+                    .iter()
+                    .filter(|event| event.id > last_event_id)
+                    .collect()
+            }
+            _ => self.snapshot.iter().collect(),
+        };
+
+        let (tx, rx) = channel(SUBSCRIBER_BUFFER.max(missed.len()));
+        for event in missed {
+            // Can't fail as the channel was just created with room for all of them
+            let _ = tx.try_send(event.clone());
+        }
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Sends a new event to every subscriber, forgetting any that have disconnected. Subscribers
+    /// that have fallen behind are dropped too, as missing an event would leave them out of date
+    /// for good. They receive what was already queued and then have to subscribe again to catch up.
+    pub fn broadcast(&mut self, name: &'static str, data: String) {
+        let event = self.next_event(name, data);
+        self.subscribers
+            .retain(|subscriber| match subscriber.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Closed(_)) => false,
+            });
+        if self.recent.len() == REPLAY_BUFFER {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(event);
+    }
+
+    /// Records the full state after every event so far, without sending it to anyone. It has an id
+    /// of its own so subscribers that start from it can resume after it.
+    pub fn set_snapshot(&mut self, name: &'static str, data: String) {
+        self.snapshot = Some(self.next_event(name, data));
+    }
+
+    /// The full state as of the latest event, if there has been one
+    pub fn snapshot(&self) -> Option<&HubEvent> {
+        self.snapshot.as_ref()
+    }
+
+    fn next_event(&mut self, name: &'static str, data: String) -> HubEvent {
+        let event = HubEvent {
+            id: self.next_id,
+            name,
+            data,
+        };
+        self.next_id += 1;
+        event
+    }
+
+    /// How many subscribers are still connected
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers
+            .iter()
+            .filter(|subscriber| !subscriber.is_closed())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventHub, HubEvent, REPLAY_BUFFER, SUBSCRIBER_BUFFER};
+    use tokio::sync::mpsc::Receiver;
+
+    fn broadcast(hub: &mut EventHub, data: &str) {
+        hub.broadcast("battle_deltas", data.into());
+        hub.set_snapshot("battle_state", format!("snapshot {data}"));
+    }
+
+    fn received(rx: &mut Receiver<HubEvent>) -> Vec<String> {
+        let mut received = vec![];
+        while let Ok(event) = rx.try_recv() {
+            received.push(event.data);
+        }
+        received
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let mut hub = EventHub::default();
+        let mut first = hub.subscribe(None);
+        broadcast(&mut hub, "1");
+
+        let mut second = hub.subscribe(None);
+        broadcast(&mut hub, "2");
+
+        assert_eq!(received(&mut first), vec!["1", "2"]);
+        assert_eq!(
+            received(&mut second),
+            vec!["snapshot 1", "2"],
+            "Late subscribers start with the snapshot"
+        );
+
+        drop(first);
+        assert_eq!(hub.subscriber_count(), 1);
+        broadcast(&mut hub, "3");
+        assert_eq!(received(&mut second), vec!["3"]);
+    }
+
+    #[test]
+    fn test_replay_missed_events() {
+        let mut hub = EventHub::default();
+        let mut rx = hub.subscribe(None);
+        broadcast(&mut hub, "1");
+        broadcast(&mut hub, "2");
+        let last_event_id = rx.try_recv().unwrap().id;
+        drop(rx);
+
+        broadcast(&mut hub, "3");
+        let mut rx = hub.subscribe(Some(last_event_id));
+        assert_eq!(received(&mut rx), vec!["2", "3"]);
+
+        let snapshot_id = hub.subscribe(None).try_recv().unwrap().id;
+        let mut rx = hub.subscribe(Some(snapshot_id));
+        assert_eq!(
+            received(&mut rx),
+            Vec::<String>::new(),
+            "Already up to date"
+        );
+
+        let mut rx = hub.subscribe(Some(100));
+        assert_eq!(
+            received(&mut rx),
+            vec!["snapshot 3"],
+            "Unknown ids start over from the snapshot"
+        );
+
+        for i in 4..(4 + REPLAY_BUFFER) {
+            broadcast(&mut hub, &i.to_string());
+        }
+        let mut rx = hub.subscribe(Some(last_event_id));
+        assert_eq!(
+            received(&mut rx),
+            vec![format!("snapshot {}", 3 + REPLAY_BUFFER)],
+            "Starts over from the snapshot once missed events are no longer buffered"
+        );
+    }
+
+    #[test]
+    fn test_drop_lagging_subscribers() {
+        let mut hub = EventHub::default();
+        let mut lagging = hub.subscribe(None);
+        let mut keeping_up = hub.subscribe(None);
+        for i in 0..=SUBSCRIBER_BUFFER {
+            broadcast(&mut hub, &i.to_string());
+            assert_eq!(received(&mut keeping_up), vec![i.to_string()]);
+        }
+        assert_eq!(hub.subscriber_count(), 1);
+
+        let mut queued = vec![];
+        while let Ok(event) = lagging.try_recv() {
+            queued.push(event);
+        }
+        assert_eq!(queued.len(), SUBSCRIBER_BUFFER);
+        assert!(
+            lagging.is_closed(),
+            "Rather than silently missing events, the subscriber is closed"
+        );
+
+        let mut rx = hub.subscribe(queued.last().map(|event| event.id));
+        assert_eq!(
+            received(&mut rx),
+            vec![SUBSCRIBER_BUFFER.to_string()],
+            "Subscribing again catches up on what was missed"
+        );
+    }
+}
@@ -1,24 +1,283 @@
-use crate::{Action, ActionError, ActionFailure, ActionResult, CardId, CharacterId, GridLocation};
-use actix_web::{get, post, web, HttpResponse, Responder};
+use super::event_hub::{ArcEventHub, EventId, HubEvent};
+use crate::{Action, CardId, CharacterId, GridLocation};
+use actix_web::{
+    get, http::header::ContentType, post, web, Either, HttpRequest, HttpResponse, Responder,
+};
 use actix_web_lab::sse;
-use serde::Deserialize;
-use std::{sync::Arc, time::Duration};
+use futures::stream;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use tokio::sync::{
-    mpsc::{channel, error::SendError, Sender},
-    Mutex,
+    mpsc::{error::TrySendError, Sender},
+    oneshot, Mutex,
 };
 
-pub type ArcEventSender = Arc<Mutex<Option<Sender<sse::Event>>>>;
 pub type ArcServerState = Arc<Mutex<ServerState>>;
 
+/// Why a player's action wasn't taken
+pub enum ActionRejection {
+    NotYourTurn,
+    /// The action breaks the rules of the battle, for the given reason
+    Illegal(String),
+}
+
+/// Where the actor reports whether an action was accepted
+pub type ActionReply = oneshot::Sender<Result<(), ActionRejection>>;
+
 pub enum BattleServerEvent {
-    Action(ActionResult),
-    BattleRequest,
+    Action(Action, ActionReply),
 }
 
-pub struct ServerState {
-    pub event_tx: ArcEventSender,
+/// Secret handed to each human player that identifies which actor their requests are for
+pub type PlayerToken = String;
+
+/// Identifies one of the battles hosted by the server
+pub type SessionId = String;
+
+/// The channels connecting one player's requests to their [`crate::web_actor::WebActor`]
+pub struct PlayerChannels {
+    pub character_id: CharacterId,
+    /// Every connection this player has open, such as multiple tabs
+    pub events: ArcEventHub,
     pub action_tx: Sender<BattleServerEvent>,
+    /// Set while the battle is waiting on this player to act
+    pub acting: Arc<AtomicBool>,
+    /// What the player may do right now, as of the last change to the battle
+    pub legal_actions: Vec<Action>,
+    /// Whether someone has taken this seat, either through the lobby or by using its token
+    pub claimed: bool,
+}
+
+/// A single battle hosted by the server
+pub struct Session {
+    pub title: String,
+    pub description: String,
+    pub players: HashMap<PlayerToken, PlayerChannels>,
+    /// Read-only viewers of the battle, who only see what's public
+    pub spectators: ArcEventHub,
+    pub finished: bool,
+}
+
+impl Session {
+    /// The channels of the player holding `player`. Using a token takes its seat, so seats handed
+    /// out other than through the lobby stop being offered there once they're in use.
+    fn player(&mut self, player: &PlayerToken) -> Option<&PlayerChannels> {
+        let channels = self.players.get_mut(player)?;
+        channels.claimed = true;
+        Some(channels)
+    }
+}
+
+#[derive(Default)]
+pub struct ServerState {
+    pub sessions: HashMap<SessionId, Session>,
+}
+
+#[derive(Deserialize)]
+struct PlayerParams {
+    player: PlayerToken,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    UnknownSession,
+    UnknownPlayer,
+    NoOpenSeats,
+    NotYourTurn,
+    IllegalAction,
+    NotStarted,
+    BadRequest,
+}
+
+/// Why a request failed, which is sent back to the client as JSON
+#[derive(Serialize)]
+pub struct RequestError {
+    error: ErrorCode,
+    message: String,
+}
+
+impl RequestError {
+    pub fn new(error: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            error,
+            message: message.into(),
+        }
+    }
+
+    fn not_your_turn() -> Self {
+        Self::new(ErrorCode::NotYourTurn, "It isn't your turn")
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        let mut response = match self.error {
+            ErrorCode::UnknownSession => HttpResponse::NotFound(),
+            ErrorCode::UnknownPlayer => HttpResponse::Unauthorized(),
+            ErrorCode::NoOpenSeats | ErrorCode::NotYourTurn | ErrorCode::NotStarted => {
+                HttpResponse::Conflict()
+            }
+            ErrorCode::IllegalAction => HttpResponse::UnprocessableEntity(),
+            ErrorCode::BadRequest => HttpResponse::BadRequest(),
+        };
+        response.json(self)
+    }
+}
+
+fn error_response(error: ErrorCode, message: impl Into<String>) -> HttpResponse {
+    RequestError::new(error, message).into_response()
+}
+
+/// Hands an action to the actor for `player` and waits for it to be checked against the rules of
+/// the battle. Actions are turned away without waiting if the battle isn't waiting on the player.
+pub async fn submit_action(
+    state: &ArcServerState,
+    session_id: &SessionId,
+    player: &PlayerToken,
+    action: Action,
+) -> Result<(), RequestError> {
+    // Release the lock before waiting on the actor so other players aren't blocked
+    let channels = match state.lock().await.sessions.get_mut(session_id) {
+        Some(session) => session
+            .player(player)
+            .map(|channels| (channels.action_tx.clone(), channels.acting.clone())),
+        None => {
+            return Err(RequestError::new(
+                ErrorCode::UnknownSession,
+                "Unknown session",
+            ))
+        }
+    };
+    let Some((action_tx, acting)) = channels else {
+        return Err(RequestError::new(
+            ErrorCode::UnknownPlayer,
+            "Unknown player",
+        ));
+    };
+    if !acting.load(Ordering::SeqCst) {
+        return Err(RequestError::not_your_turn());
+    }
+
+    let (reply_tx, reply_rx) = oneshot::channel();
+    match action_tx.try_send(BattleServerEvent::Action(action, reply_tx)) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            return Err(RequestError::new(
+                ErrorCode::NotYourTurn,
+                "Your previous action is still being taken",
+            ))
+        }
+        Err(TrySendError::Closed(_)) => return Err(RequestError::not_your_turn()),
+    }
+    match reply_rx.await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(ActionRejection::Illegal(reason))) => {
+            Err(RequestError::new(ErrorCode::IllegalAction, reason))
+        }
+        // The actor stops listening once the battle is over
+        Ok(Err(ActionRejection::NotYourTurn)) | Err(_) => Err(RequestError::not_your_turn()),
+    }
+}
+
+/// Responds to an action request with nothing but its status if it was taken
+async fn respond_to_action(
+    state: &ArcServerState,
+    session_id: &SessionId,
+    player: &PlayerToken,
+    action: Action,
+) -> HttpResponse {
+    match submit_action(state, session_id, player, action).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct LobbyEntry<'session> {
+    id: &'session SessionId,
+    title: &'session str,
+    description: &'session str,
+    open_seats: usize,
+    spectators: usize,
+}
+
+/// Lists the battles that are still being played, sorted by id
+#[get("/lobby")]
+async fn handle_lobby(state: web::Data<ArcServerState>) -> impl Responder {
+    // Release the server lock before counting spectators so a busy session doesn't hold up others
+    let sessions: Vec<(SessionId, String, String, usize, ArcEventHub)> = {
+        let state = state.lock().await;
+        state
+            .sessions
+            .iter()
+            .filter(|(_, session)| !session.finished)
+            .map(|(id, session)| {
+                (
+                    id.clone(),
+                    session.title.clone(),
+                    session.description.clone(),
+                    session
+                        .players
+                        .values()
+                        .filter(|channels| !channels.claimed)
+                        .count(),
+                    session.spectators.clone(),
+                )
+            })
+            .collect()
+    };
+
+    let mut entries: Vec<LobbyEntry> = vec![];
+    for (id, title, description, open_seats, spectators) in &sessions {
+        entries.push(LobbyEntry {
+            id,
+            title,
+            description,
+            open_seats: *open_seats,
+            spectators: spectators.lock().await.subscriber_count(),
+        });
+    }
+    entries.sort_by(|a, b| a.id.cmp(b.id));
+    HttpResponse::Ok().json(entries)
+}
+
+#[derive(Serialize)]
+struct JoinResponse<'session> {
+    player: &'session PlayerToken,
+    character_id: CharacterId,
+}
+
+/// Claims the first open seat in a battle, handing back the token to play it with
+#[post("/join")]
+async fn handle_join(
+    session_id: web::Path<SessionId>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    let mut state = state.lock().await;
+    let Some(session) = state.sessions.get_mut(session_id.as_str()) else {
+        return error_response(ErrorCode::UnknownSession, "Unknown session");
+    };
+    let seat = session
+        .players
+        .iter_mut()
+        .filter(|(_, channels)| !channels.claimed)
+        .min_by_key(|(_, channels)| channels.character_id);
+    match seat {
+        Some((player, channels)) if !session.finished => {
+            channels.claimed = true;
+            HttpResponse::Ok().json(JoinResponse {
+                player,
+                character_id: channels.character_id,
+            })
+        }
+        _ => error_response(ErrorCode::NoOpenSeats, "No open seats"),
+    }
 }
 
 #[derive(Deserialize)]
@@ -29,20 +288,18 @@ struct ActParams {
 
 #[post("/act")]
 async fn handle_act(
+    session_id: web::Path<SessionId>,
     info: web::Json<ActParams>,
+    player: web::Query<PlayerParams>,
     state: web::Data<ArcServerState>,
 ) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::Action(ActionResult::Ok(Action::Act(
-            CardId::new(info.card_id),
-            CharacterId::new(info.target_id),
-        ))))
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+    respond_to_action(
+        &state,
+        &session_id,
+        &player.player,
+        Action::Act(CardId::new(info.card_id), CharacterId::new(info.target_id)),
+    )
+    .await
 }
 
 #[derive(Deserialize)]
@@ -59,62 +316,384 @@ struct MoveParams {
 
 #[post("/move")]
 async fn handle_move(
+    session_id: web::Path<SessionId>,
     info: web::Json<MoveParams>,
+    player: web::Query<PlayerParams>,
     state: web::Data<ArcServerState>,
 ) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::Action(ActionResult::Ok(Action::Move(
+    respond_to_action(
+        &state,
+        &session_id,
+        &player.player,
+        Action::Move(
             CharacterId::new(info.target_id),
             GridLocation {
                 x: info.to.x,
                 y: info.to.y,
             },
-        ))))
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+        ),
+    )
+    .await
 }
 
 #[post("/pass")]
-async fn handle_pass(state: web::Data<ArcServerState>) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::Action(ActionResult::Ok(Action::Pass)))
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+async fn handle_pass(
+    session_id: web::Path<SessionId>,
+    player: web::Query<PlayerParams>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    respond_to_action(&state, &session_id, &player.player, Action::Pass).await
 }
 
-#[get("/info")]
-async fn handle_info(state: web::Data<ArcServerState>) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::BattleRequest)
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+#[derive(Deserialize)]
+pub struct ViewerParams {
+    /// Views the battle as a spectator if missing
+    pub player: Option<PlayerToken>,
 }
 
+/// Finds the events sent to the viewer, which is either a player or a spectator
+pub fn viewer_events(
+    state: &mut ServerState,
+    session_id: &SessionId,
+    player: &Option<PlayerToken>,
+) -> Result<ArcEventHub, RequestError> {
+    let Some(session) = state.sessions.get_mut(session_id) else {
+        return Err(RequestError::new(
+            ErrorCode::UnknownSession,
+            "Unknown session",
+        ));
+    };
+    match player {
+        Some(player) => match session.player(player) {
+            Some(channels) => Ok(channels.events.clone()),
+            None => Err(RequestError::new(
+                ErrorCode::UnknownPlayer,
+                "Unknown player",
+            )),
+        },
+        None => Ok(session.spectators.clone()),
+    }
+}
+
+/// The battle as the viewer currently sees it, for clients that would rather poll than subscribe.
+/// Responds with the same [`super::BattleState`] subscribers are sent.
+#[get("/state")]
+async fn handle_state(
+    session_id: web::Path<SessionId>,
+    params: web::Query<ViewerParams>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    let events = match viewer_events(&mut *state.lock().await, &session_id, &params.player) {
+        Ok(events) => events,
+        Err(error) => return error.into_response(),
+    };
+    let events = events.lock().await;
+    match events.snapshot() {
+        Some(snapshot) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(snapshot.data.clone()),
+        None => error_response(ErrorCode::NotStarted, "The battle hasn't started yet"),
+    }
+}
+
+/// An action in the same form it would be sent to the server
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LegalAction<'action> {
+    Act {
+        card_id: CardId,
+        target_id: CharacterId,
+    },
+    Move {
+        target_id: CharacterId,
+        to: &'action GridLocation,
+    },
+    Pass,
+}
+
+impl<'action> From<&'action Action> for LegalAction<'action> {
+    fn from(action: &'action Action) -> Self {
+        match action {
+            Action::Act(card_id, target_id) => Self::Act {
+                card_id: *card_id,
+                target_id: *target_id,
+            },
+            Action::Move(target_id, to) => Self::Move {
+                target_id: *target_id,
+                to,
+            },
+            Action::Pass => Self::Pass,
+        }
+    }
+}
+
+/// Lists what the player may do right now, which is nothing unless it's their turn
+#[get("/actions")]
+async fn handle_actions(
+    session_id: web::Path<SessionId>,
+    player: web::Query<PlayerParams>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    let mut state = state.lock().await;
+    let Some(session) = state.sessions.get_mut(session_id.as_str()) else {
+        return error_response(ErrorCode::UnknownSession, "Unknown session");
+    };
+    let Some(channels) = session.player(&player.player) else {
+        return error_response(ErrorCode::UnknownPlayer, "Unknown player");
+    };
+    HttpResponse::Ok().json(
+        channels
+            .legal_actions
+            .iter()
+            .map(LegalAction::from)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Browsers send this when reconnecting with the id of the last event they received
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+
 #[get("/sse")]
-async fn handle_sse(state: web::Data<ArcServerState>) -> impl Responder {
-    let (tx, rx) = channel(10);
+async fn handle_sse(
+    request: HttpRequest,
+    session_id: web::Path<SessionId>,
+    params: web::Query<ViewerParams>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    let events = match viewer_events(&mut *state.lock().await, &session_id, &params.player) {
+        Ok(events) => events,
+        Err(error) => return Either::Right(error.into_response()),
+    };
 
-    state.lock().await.event_tx.lock().await.replace(tx);
+    let last_event_id = request
+        .headers()
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<EventId>().ok());
+    let rx = events.lock().await.subscribe(last_event_id);
+    let events = stream::unfold(rx, |mut rx| async move {
+        let event: HubEvent = rx.recv().await?;
+        Some((
+            sse::Data::new(event.data)
+                .event(event.name)
+                .id(event.id.to_string())
+                .into(),
+            rx,
+        ))
+    });
 
-    sse::Sse::from_infallible_receiver(rx).with_retry_duration(Duration::from_secs(10))
+    Either::Left(
+        sse::Sse::from_infallible_stream(events).with_retry_duration(Duration::from_secs(10)),
+    )
 }
 
-impl From<SendError<sse::Event>> for ActionError {
-    fn from(send_error: SendError<sse::Event>) -> Self {
-        Self::Failure(ActionFailure {
-            message: send_error.to_string(),
-        })
+#[cfg(test)]
+pub(super) mod tests {
+    use super::{
+        handle_act, handle_actions, handle_join, handle_lobby, handle_pass, ActionRejection,
+        ArcServerState, BattleServerEvent, PlayerChannels, Session,
+    };
+    use crate::{Action, CharacterId};
+    use actix_web::{
+        dev::{Service, ServiceResponse},
+        http::StatusCode,
+        test, web, App,
+    };
+    use serde_json::{json, Value};
+    use std::{collections::HashMap, sync::atomic::Ordering};
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    /// A session with a seat for each token, along with what each seat's actor would receive
+    pub(crate) fn session(tokens: &[&str]) -> (Session, Vec<Receiver<BattleServerEvent>>) {
+        let mut players = HashMap::new();
+        let mut receivers = vec![];
+        for (id, token) in tokens.iter().enumerate() {
+            let (action_tx, action_rx) = channel(1);
+            players.insert(
+                token.to_string(),
+                PlayerChannels {
+                    character_id: CharacterId::new(id),
+                    events: Default::default(),
+                    action_tx,
+                    acting: Default::default(),
+                    legal_actions: vec![],
+                    claimed: false,
+                },
+            );
+            receivers.push(action_rx);
+        }
+        let session = Session {
+            title: "Title".into(),
+            description: "Description".into(),
+            players,
+            spectators: Default::default(),
+            finished: false,
+        };
+        (session, receivers)
+    }
+
+    /// Stands in for a player's actor, which only allows passing
+    pub(crate) fn allow_only_passing(mut action_rx: Receiver<BattleServerEvent>) {
+        tokio::spawn(async move {
+            while let Some(BattleServerEvent::Action(action, reply)) = action_rx.recv().await {
+                let _ = reply.send(match action {
+                    Action::Pass => Ok(()),
+                    _ => Err(ActionRejection::Illegal("Out of range".into())),
+                });
+            }
+        });
+    }
+
+    async fn body(response: ServiceResponse) -> Value {
+        serde_json::from_slice(&test::read_body(response).await).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_lobby_and_join() {
+        let state = ArcServerState::default();
+        let (session, _receivers) = session(&["first", "second", "third"]);
+        state.lock().await.sessions.insert("battle".into(), session);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .service(handle_lobby)
+                .service(
+                    web::scope("/sessions/{session_id}")
+                        .service(handle_join)
+                        .service(handle_actions),
+                ),
+        )
+        .await;
+        let lobby = || async {
+            let response = app
+                .call(test::TestRequest::get().uri("/lobby").to_request())
+                .await
+                .unwrap();
+            body(response).await[0]["open_seats"].clone()
+        };
+        let join = |session_id: &str| {
+            app.call(
+                test::TestRequest::post()
+                    .uri(&format!("/sessions/{session_id}/join"))
+                    .to_request(),
+            )
+        };
+
+        assert_eq!(lobby().await, 3);
+
+        let response = app
+            .call(
+                test::TestRequest::get()
+                    .uri("/sessions/battle/actions?player=first")
+                    .to_request(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            lobby().await,
+            2,
+            "Using a token handed out at startup takes its seat"
+        );
+
+        let response = join("battle").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body(response).await,
+            json!({ "player": "second", "character_id": 1 }),
+            "Seats already in use aren't handed out again"
+        );
+        assert_eq!(body(join("battle").await.unwrap()).await["player"], "third");
+        assert_eq!(lobby().await, 0);
+
+        let response = join("battle").await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body(response).await["error"], "no_open_seats");
+
+        let response = join("missing").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body(response).await["error"], "unknown_session");
+
+        state
+            .lock()
+            .await
+            .sessions
+            .get_mut("battle")
+            .unwrap()
+            .finished = true;
+        let response = app
+            .call(test::TestRequest::get().uri("/lobby").to_request())
+            .await
+            .unwrap();
+        assert_eq!(
+            body(response).await,
+            json!([]),
+            "Finished battles aren't listed"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_action_errors() {
+        let state = ArcServerState::default();
+        let (session, mut receivers) = session(&["acting", "waiting"]);
+        session.players["acting"]
+            .acting
+            .store(true, Ordering::SeqCst);
+        state.lock().await.sessions.insert("battle".into(), session);
+        allow_only_passing(receivers.remove(0));
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(state)).service(
+                web::scope("/sessions/{session_id}")
+                    .service(handle_act)
+                    .service(handle_pass),
+            ),
+        )
+        .await;
+        let post = |uri: &str, body: Value| {
+            app.call(
+                test::TestRequest::post()
+                    .uri(uri)
+                    .set_json(body)
+                    .to_request(),
+            )
+        };
+
+        let response = post("/sessions/battle/pass?player=acting", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = post(
+            "/sessions/battle/act?player=acting",
+            json!({ "card_id": 0, "target_id": 1 }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body(response).await,
+            json!({ "error": "illegal_action", "message": "Out of range" })
+        );
+
+        let response = post("/sessions/battle/pass?player=waiting", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            body(response).await,
+            json!({ "error": "not_your_turn", "message": "It isn't your turn" })
+        );
+
+        let response = post("/sessions/battle/pass?player=unknown", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(body(response).await["error"], "unknown_player");
+
+        let response = post("/sessions/unknown/pass?player=acting", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body(response).await["error"], "unknown_session");
     }
 }
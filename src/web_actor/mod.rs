@@ -1,6 +1,10 @@
+mod event_hub;
 mod handlers;
 mod server;
 #[allow(clippy::module_inception)]
 mod web_actor;
+mod websocket;
 
-pub use web_actor::WebActor;
+pub use handlers::SessionId;
+pub use server::ServerConfig;
+pub use web_actor::{BattleState, WebActor, WebActorServer, WebSession};
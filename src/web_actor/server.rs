@@ -6,29 +6,83 @@ use actix_web::{
 use futures::executor::block_on;
 use std::{
     marker::PhantomData,
-    path::Path,
+    net::SocketAddr,
+    path::PathBuf,
     sync::Arc,
     thread::{self, JoinHandle},
 };
+#[cfg(not(embedded_client))]
 use std::{
     process::Command,
     sync::atomic::{AtomicBool, Ordering},
 };
 use tokio::sync::Mutex;
 
-use crate::web_actor::handlers::{handle_act, handle_info, handle_move, handle_pass, handle_sse};
+use crate::web_actor::{
+    handlers::{
+        handle_act, handle_actions, handle_join, handle_lobby, handle_move, handle_pass,
+        handle_sse, handle_state,
+    },
+    websocket::handle_websocket,
+};
+
+/// Where the web server listens and what it serves
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub host: String,
+    /// Port to listen on. 0 lets the OS pick any free port.
+    pub port: u16,
+    /// Battle assets, such as character images, served to clients under `ref/`
+    pub asset_directory: Option<PathBuf>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".into(),
+            port: 8000,
+            asset_directory: None,
+        }
+    }
+}
 
 pub struct Server<T> {
     _phantom: PhantomData<T>,
+    /// The address the server is actually bound to
+    pub address: SocketAddr,
     server_thread: Option<JoinHandle<std::io::Result<()>>>,
     server_handle: ServerHandle,
 
-    #[cfg(debug_assertions)]
+    #[cfg(not(embedded_client))]
     asset_build_thread: Option<JoinHandle<()>>,
-    #[cfg(debug_assertions)]
+    #[cfg(not(embedded_client))]
     asset_build_thread_terminate: Arc<AtomicBool>,
 }
 
+/// The compiled web client, embedded so release builds can be shipped as a single executable
+#[cfg(embedded_client)]
+mod embedded {
+    include!(concat!(env!("OUT_DIR"), "/static_assets.rs"));
+}
+
+#[cfg(embedded_client)]
+async fn handle_embedded_asset(path: web::Path<(String, String)>) -> actix_web::HttpResponse {
+    let (_session_id, mut path) = path.into_inner();
+    // Directories, such as the session itself, load the client
+    if path.is_empty() || path.ends_with('/') {
+        path.push_str("index.html");
+    }
+    match embedded::STATIC_ASSETS
+        .iter()
+        .find(|(asset_path, _)| *asset_path == path)
+    {
+        Some((_, contents)) => actix_web::HttpResponse::Ok()
+            .content_type(mime_guess::from_path(&path).first_or_octet_stream())
+            .body(*contents),
+        None => actix_web::HttpResponse::NotFound().finish(),
+    }
+}
+
 #[actix_web::main]
 async fn server_main<T>(server: actix_web::dev::Server) -> std::io::Result<()> {
     server.await
@@ -37,17 +91,15 @@ async fn server_main<T>(server: actix_web::dev::Server) -> std::io::Result<()> {
 impl<T: Sync + Send + 'static> Server<T> {
     pub fn new(
         server_state: Arc<Mutex<T>>,
-        additional_static_asset_directory: Option<&Path>,
+        config: &ServerConfig,
     ) -> Result<Server<T>, std::io::Error>
 where {
-        let host = "0.0.0.0";
-        let port = 8000;
+        #[cfg(not(embedded_client))]
         const STATIC_HOSTING_DIR: &str = concat!(env!("OUT_DIR"), "/static");
-        let additional_static_asset_directory =
-            additional_static_asset_directory.map(Path::to_path_buf);
+        let additional_static_asset_directory = config.asset_directory.clone();
 
-        let server: actix_web::dev::Server = HttpServer::new(move || {
-            let mut app = App::new()
+        let http_server = HttpServer::new(move || {
+            let app = App::new()
                 .app_data(web::Data::new(server_state.clone()))
                 .wrap(
                     ErrorHandlers::new().default_handler(|service_response: ServiceResponse| {
@@ -62,55 +114,80 @@ where {
                         ))
                     }),
                 )
+                .service(handle_lobby);
+            // The client only uses relative paths so each battle is reached under its own session
+            let mut session = web::scope("/sessions/{session_id}")
+                .service(handle_join)
                 .service(handle_act)
                 .service(handle_move)
                 .service(handle_pass)
-                .service(handle_info)
-                .service(handle_sse);
+                .service(handle_state)
+                .service(handle_actions)
+                .service(handle_sse)
+                .service(handle_websocket);
             if let Some(dir) = &additional_static_asset_directory {
-                app = app.service(actix_files::Files::new("/ref", dir.clone()).use_etag(true));
+                session =
+                    session.service(actix_files::Files::new("/ref", dir.clone()).use_etag(true));
             }
             // Must come after the additional directory to ensure resolution
-            app = app.service(actix_files::Files::new("/", STATIC_HOSTING_DIR).use_etag(true));
-            app
+            #[cfg(not(embedded_client))]
+            {
+                session = session
+                    .service(actix_files::Files::new("/", STATIC_HOSTING_DIR).use_etag(true));
+            }
+            #[cfg(embedded_client)]
+            {
+                session = session.route("/{path:.*}", web::get().to(handle_embedded_asset));
+            }
+            app.service(session)
         })
         .disable_signals()
-        .bind((host, port))
-        .unwrap()
-        .run();
+        .bind((config.host.as_str(), config.port))?;
+        // Port 0 is only resolved to a real port once bound
+        let address = http_server.addrs()[0];
+        let server = http_server.run();
         let server_handle = server.handle();
-        println!("Started server on http://{}:{}/index.html", host, port);
+        println!(
+            "Started server on http://{}, open games are listed at http://{}/lobby",
+            address, address
+        );
+        #[cfg(not(embedded_client))]
         println!("Serving static assets from {}", STATIC_HOSTING_DIR);
         let server_thread = thread::spawn(|| server_main::<T>(server));
 
-        #[cfg(debug_assertions)]
+        #[cfg(not(embedded_client))]
         {
             let asset_build_thread_terminate: Arc<AtomicBool> = Default::default();
             let thread_bool = asset_build_thread_terminate.clone();
             Ok(Self {
                 _phantom: PhantomData,
+                address,
                 server_thread: Some(server_thread),
                 server_handle,
-                asset_build_thread: Some(thread::spawn(move || {
-                    while !thread_bool.load(Ordering::Relaxed) {
-                        if let Ok(status) = Command::new("npm")
-                            .args(["run", "build-server"])
-                            .env("OUT_DIR", env!("OUT_DIR"))
-                            .status()
-                        {
-                            if !status.success() {
-                                println!("Asset build failed!");
+                // Tests don't load the client, and the watching build would never let them finish
+                asset_build_thread: (!cfg!(test)).then(|| {
+                    thread::spawn(move || {
+                        while !thread_bool.load(Ordering::Relaxed) {
+                            if let Ok(status) = Command::new("npm")
+                                .args(["run", "build-server"])
+                                .env("OUT_DIR", env!("OUT_DIR"))
+                                .status()
+                            {
+                                if !status.success() {
+                                    println!("Asset build failed!");
+                                }
                             }
                         }
-                    }
-                })),
+                    })
+                }),
                 asset_build_thread_terminate,
             })
         }
-        #[cfg(not(debug_assertions))]
+        #[cfg(embedded_client)]
         {
             Ok(Self {
                 _phantom: PhantomData,
+                address,
                 server_thread: Some(server_thread),
                 server_handle,
             })
@@ -120,9 +197,14 @@ where {
 
 impl<T> Drop for Server<T> {
     fn drop(&mut self) {
-        self.asset_build_thread_terminate
-            .store(true, Ordering::Relaxed);
-        self.asset_build_thread.take().unwrap().join().unwrap();
+        #[cfg(not(embedded_client))]
+        {
+            self.asset_build_thread_terminate
+                .store(true, Ordering::Relaxed);
+            if let Some(asset_build_thread) = self.asset_build_thread.take() {
+                asset_build_thread.join().unwrap();
+            }
+        }
         block_on(self.server_handle.stop(true));
         self.server_thread
             .take()
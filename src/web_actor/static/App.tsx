@@ -1,10 +1,11 @@
 import React, { useEffect, useState } from "react";
-import { ActionTarget, BattleState, CardId } from "./battle";
+import { BattleState, CardId } from "./battle";
 import * as messages from "./messages.js";
 import Card from "./Card.js";
 import Character from "./Character.js";
 import BattleHistory from "./BattleHistory.js";
-import { getCardTarget, getLivingEnemies } from "./utils.js";
+import { describeOutcome, getCardTarget, getLivingEnemies } from "./utils.js";
+import { applyDeltas } from "./deltas.js";
 import { takeAction } from "./state.js";
 import { StoryCard } from "./StoryCard.js";
 import { GameBoard } from "./GameBoard.js";
@@ -17,16 +18,27 @@ export default function App() {
   const [showIntroState, setShowIntroState] = useState<boolean>(false);
 
   useEffect(() => {
-    // Throwaway
-    fetch("/info");
-
+    // The whole battle is sent on connecting, followed by what changes
     const onBattleState = (e) => {
       setBattleState(JSON.parse(e.data));
     };
+    const onBattleDeltas = (e) => {
+      const deltas = JSON.parse(e.data);
+      setBattleState((state) => {
+        const battle = applyDeltas(state?.battle, deltas);
+        // Who is being played only comes with the full state, so until then the
+        // battle is shown as a spectator would see it
+        return battle
+          ? { character_id: state?.character_id ?? null, battle }
+          : state;
+      });
+    };
 
     messages.addEventListener("battle_state", onBattleState);
+    messages.addEventListener("battle_deltas", onBattleDeltas);
     return () => {
       messages.removeEventListener("battle_state", onBattleState);
+      messages.removeEventListener("battle_deltas", onBattleDeltas);
     };
   }, [setBattleState]);
 
@@ -57,18 +69,23 @@ export default function App() {
         <></>
       )}
       <div style={{ flexGrow: 5 }}>
+        {battle.outcome ? (
+          <h2>{describeOutcome(battle, battle.outcome)}</h2>
+        ) : null}
         <div
           style={{
             display: "flex",
             gap: "4em",
           }}
         >
-          <Character
-            isPlayer={true}
-            characterId={characterId}
-            draggedCard={dragState}
-            battle={battle}
-          />
+          {characterId !== null ? (
+            <Character
+              isPlayer={true}
+              characterId={characterId}
+              draggedCard={dragState}
+              battle={battle}
+            />
+          ) : null}
           {Object.values(battle.characters)
             .filter((character) => character.id !== characterId)
             .map((character) => (
@@ -95,41 +112,49 @@ export default function App() {
               gap: "1em",
             }}
           >
-            {battle.characters[characterId].hand.map((cardId) => {
-              const card = battle.cards[cardId];
-              const target = getCardTarget(card);
-              let defaultAction: undefined | (() => Promise<void>);
-              if (target === ActionTarget.Me) {
-                defaultAction = async () =>
-                  await takeAction(card.id, characterId);
-              } else if (target === ActionTarget.Others) {
-                const enemies = getLivingEnemies(battle, characterId);
-                if (enemies.length == 1) {
-                  defaultAction = async () =>
-                    await takeAction(card.id, enemies[0].id);
-                }
-              }
-              return (
-                <li key={cardId}>
-                  <Card
-                    card={card}
-                    onDragStart={() => setDragState(cardId)}
-                    onDragEnd={() => setDragState(undefined)}
-                    onClick={async () => {
-                      // Take default actions when clicking buttons
-                      if (defaultAction) {
-                        await defaultAction();
-                      }
-                    }}
-                    hasDefaultAction={defaultAction !== undefined}
-                  />
-                </li>
-              );
-            })}
+            {characterId === null
+              ? null
+              : (battle.characters[characterId].hand ?? []).map((cardId) => {
+                  const card = battle.cards[cardId];
+                  const target = getCardTarget(card);
+                  let defaultAction: undefined | (() => Promise<unknown>);
+                  if (target === "Me") {
+                    defaultAction = async () =>
+                      await takeAction(card.id, characterId);
+                  } else if (target === "Others") {
+                    const enemies = getLivingEnemies(battle, characterId);
+                    if (enemies.length == 1) {
+                      defaultAction = async () =>
+                        await takeAction(card.id, enemies[0].id);
+                    }
+                  }
+                  return (
+                    <li key={cardId}>
+                      <Card
+                        card={card}
+                        onDragStart={() => setDragState(cardId)}
+                        onDragEnd={() => setDragState(undefined)}
+                        onClick={async () => {
+                          // Take default actions when clicking buttons
+                          if (defaultAction) {
+                            await defaultAction();
+                          }
+                        }}
+                        hasDefaultAction={defaultAction !== undefined}
+                      />
+                    </li>
+                  );
+                })}
           </ul>
         </div>
       </div>
       <div style={{ flexGrow: 2 }}>
+        <h3>Turn order</h3>
+        <ol>
+          {battle.turn_order.map((id, index) => (
+            <li key={index}>{battle.characters[id].name}</li>
+          ))}
+        </ol>
         <BattleHistory history={battle.history} />
       </div>
     </div>
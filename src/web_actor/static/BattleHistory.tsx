@@ -1,7 +1,7 @@
 import React from "react";
-import { BattleHistoryEntry, TypedText } from "./battle";
+import { BattleHistoryEntry, BattleTextEntry, TemplateEntry } from "./battle";
 
-function convert(typedText: TypedText): React.ReactNode {
+function convert(typedText: TemplateEntry<BattleTextEntry>): React.ReactNode {
   if ("Text" in typedText) {
     return typedText["Text"];
   } else if ("Typed" in typedText) {
@@ -16,7 +16,7 @@ function convert(typedText: TypedText): React.ReactNode {
     }
   } else {
     throw new Error(
-      `Invalid TypedText encountered: ${JSON.stringify(typedText)}`,
+      `Invalid TemplateEntry encountered: ${JSON.stringify(typedText)}`,
     );
   }
 }
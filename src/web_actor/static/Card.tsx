@@ -1,5 +1,5 @@
 import React from "react";
-import { ActionTarget, Battle, Card, CardId } from "./battle";
+import { Battle, Card, CardId } from "./battle";
 import { getCardTarget } from "./utils";
 
 export function isCardEligible(
@@ -10,11 +10,11 @@ export function isCardEligible(
   const card = battle.cards[cardId];
   const target = getCardTarget(card);
   switch (target) {
-    case ActionTarget.Me:
+    case "Me":
       return isPlayer;
-    case ActionTarget.Others:
+    case "Others":
       return !isPlayer;
-    case ActionTarget.Any:
+    case "Any":
       return true;
   }
 }
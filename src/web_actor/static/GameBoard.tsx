@@ -22,16 +22,25 @@ export function GameBoard(props: {
         {battle.board.grid.members.map((row, y) => (
           <tr key={y}>
             {row.map((col, x) => {
+              const isWall = col === "Wall";
+              const item = isWall ? null : col;
+              const isVisible = battle.board.visible?.[y][x] ?? true;
               let image: string | undefined;
               let character: Character | undefined;
-              if (col?.Character !== undefined) {
-                character = battle.characters[col.Character];
+              const card =
+                item !== null && "Card" in item
+                  ? battle.cards[item.Card]
+                  : undefined;
+              if (item !== null && "Character" in item) {
+                character = battle.characters[item.Character];
                 if (character.image !== null) {
                   image = `url(${assetPath(character.image)})`;
                 }
                 if (character.health == 0) {
                   image = `url(${assetPath("skull.png")})`;
                 }
+              } else if (item !== null && "Remains" in item) {
+                image = `url(${assetPath("skull.png")})`;
               }
               const curLocation: Coordinate = { x, y };
               const isSelectedSquare =
@@ -39,7 +48,9 @@ export function GameBoard(props: {
                 selectedSquare.x === x &&
                 selectedSquare.y === y;
               const isPlayer =
-                props.battleState.character_id === col?.Character;
+                item !== null &&
+                "Character" in item &&
+                props.battleState.character_id === item.Character;
 
               // Only ineligible if there is actively a card being dragged and that card isn't eligible.
               const isIneligible =
@@ -55,6 +66,11 @@ export function GameBoard(props: {
                     width: "1em",
                     height: "1em",
                     textAlign: "center",
+                    backgroundColor: isWall
+                      ? "#5d4037"
+                      : isVisible
+                        ? undefined
+                        : "#9e9e9e",
                     backgroundImage: image,
                     backgroundSize: "contain",
                     backgroundRepeat: "no-repeat",
@@ -91,19 +107,25 @@ export function GameBoard(props: {
                         selectedSquare !== undefined &&
                         isAdjacent(selectedSquare, curLocation)
                       ) {
-                        const item =
+                        const selectedItem =
                           battle.board.grid.members[selectedSquare.y][
                             selectedSquare.x
                           ];
-                        if (item?.Character !== undefined) {
+                        if (
+                          selectedItem !== null &&
+                          selectedItem !== "Wall" &&
+                          "Character" in selectedItem
+                        ) {
                           setSelectedSquare(undefined);
-                          await move(item?.Character, curLocation);
+                          await move(selectedItem.Character, curLocation);
                         }
                       }
                     }
                   }}
-                  title={character?.name}
-                ></td>
+                  title={character?.name ?? card?.name}
+                >
+                  {card ? "🃏" : null}
+                </td>
               );
             })}
           </tr>
@@ -0,0 +1,95 @@
+import { Battle, BattleDelta, Board, CharacterId } from "./battle";
+
+// Returns a copy of the board's grid without the character on it
+function removeCharacter(board: Board, characterId: CharacterId): Board {
+  return {
+    ...board,
+    grid: {
+      ...board.grid,
+      members: board.grid.members.map((row) =>
+        row.map((item) =>
+          item !== null &&
+          item !== "Wall" &&
+          "Character" in item &&
+          item.Character === characterId
+            ? null
+            : item,
+        ),
+      ),
+    },
+  };
+}
+
+function applyDelta(battle: Battle, delta: BattleDelta): Battle {
+  switch (delta.type) {
+    case "Reset":
+      return delta.battle;
+    case "RoundChanged":
+      return {
+        ...battle,
+        round: delta.round,
+        rounds_without_health_change: delta.rounds_without_health_change,
+      };
+    case "TurnStarted":
+    case "CardPlayed":
+      // Nothing changes on their own but they can be animated
+      return battle;
+    case "HealthChanged":
+      return {
+        ...battle,
+        characters: {
+          ...battle.characters,
+          [delta.character_id]: {
+            ...battle.characters[delta.character_id],
+            health: delta.health,
+          },
+        },
+      };
+    case "CharacterChanged":
+      return {
+        ...battle,
+        characters: {
+          ...battle.characters,
+          [delta.character.id]: delta.character,
+        },
+      };
+    case "CharacterHidden": {
+      const characters = { ...battle.characters };
+      delete characters[delta.character_id];
+      return {
+        ...battle,
+        characters,
+        board: removeCharacter(battle.board, delta.character_id),
+      };
+    }
+    case "BoardChanged":
+      return { ...battle, board: delta.board };
+    case "CharacterMoved": {
+      const board = removeCharacter(battle.board, delta.character_id);
+      board.grid.members[delta.to.y][delta.to.x] = {
+        Character: delta.character_id,
+      };
+      return { ...battle, board };
+    }
+    case "TurnOrderChanged":
+      return { ...battle, turn_order: delta.turn_order };
+    case "HistoryAppended":
+      return { ...battle, history: [...battle.history, ...delta.entries] };
+    case "BattleEnded":
+      return { ...battle, outcome: delta.outcome };
+  }
+}
+
+// Without a battle to start from, deltas can only be applied from a reset on
+export function applyDeltas(
+  battle: Battle | undefined,
+  deltas: BattleDelta[],
+): Battle | undefined {
+  return deltas.reduce<Battle | undefined>(
+    (battle, delta) =>
+      delta.type === "Reset"
+        ? delta.battle
+        : battle && applyDelta(battle, delta),
+    battle,
+  );
+}
@@ -1,25 +1,100 @@
-let evtSource: EventSource;
+import { playerPath } from "./utils.ts";
 
-export function init(onConnected?: () => void) {
+// Matches the retry duration the server asks server-sent events to use
+const RECONNECT_DELAY_MS = 10000;
+
+type Listener = (event: { data: string }) => void;
+
+const listeners = new Map<string, Set<Listener>>();
+
+let socket: WebSocket | null = null;
+let lastEventId: number | null = null;
+let nextRequestId = 1;
+// Resolves with the error the server responded with, or null if accepted
+const pendingRequests = new Map<number, (error: object | null) => void>();
+
+function dispatch(name: string, data: string) {
+  listeners.get(name)?.forEach((callback) => callback({ data }));
+}
+
+function webSocketUrl(): URL {
+  const url = new URL(playerPath("ws"), window.location.href);
+  url.protocol = url.protocol === "https:" ? "wss:" : "ws:";
+  if (lastEventId !== null) {
+    url.searchParams.set("last_event_id", `${lastEventId}`);
+  }
+  return url;
+}
+
+// Server-sent events are used instead if a WebSocket can't be opened at all
+function initEventSource(onConnected?: () => void) {
   console.log("Init sse");
-  evtSource = new EventSource("/sse");
+  const evtSource = new EventSource(playerPath("sse"));
   evtSource.onopen = onConnected ?? null;
-
   evtSource.onerror = (err) => {
     console.error("EventSource failure:", err);
   };
+  for (const name of ["battle_state", "battle_deltas"]) {
+    evtSource.addEventListener(name, (e) => dispatch(name, e.data));
+  }
+}
+
+export function init(onConnected?: () => void) {
+  console.log("Init WebSocket");
+  const ws = new WebSocket(webSocketUrl());
+  let opened = false;
+  ws.onopen = () => {
+    opened = true;
+    socket = ws;
+    onConnected?.();
+  };
+  ws.onmessage = (e) => {
+    const message = JSON.parse(e.data);
+    if (message.type === "event") {
+      lastEventId = message.id;
+      dispatch(message.name, JSON.stringify(message.data));
+    } else {
+      pendingRequests.get(message.request_id)?.(
+        message.type === "ack" ? null : message,
+      );
+      pendingRequests.delete(message.request_id);
+    }
+  };
+  ws.onclose = () => {
+    socket = null;
+    for (const resolve of pendingRequests.values()) {
+      resolve({ error: "disconnected", message: "Lost connection" });
+    }
+    pendingRequests.clear();
+    if (opened) {
+      setTimeout(() => init(onConnected), RECONNECT_DELAY_MS);
+    } else {
+      initEventSource(onConnected);
+    }
+  };
+}
+
+// Sends a request over the WebSocket, resolving to the error the server
+// responded with if any. Returns null if there's no WebSocket to send it on.
+export function sendRequest(request: object): Promise<object | null> | null {
+  if (socket === null) {
+    return null;
+  }
+  const id = nextRequestId++;
+  const response = new Promise<object | null>((resolve) =>
+    pendingRequests.set(id, resolve),
+  );
+  socket.send(JSON.stringify({ ...request, id }));
+  return response;
 }
 
-export function addEventListener(
-  event: string,
-  callback: (MessageEvent) => void,
-) {
-  evtSource.addEventListener(event, callback);
+export function addEventListener(event: string, callback: Listener) {
+  if (!listeners.has(event)) {
+    listeners.set(event, new Set());
+  }
+  listeners.get(event)!.add(callback);
 }
 
-export function removeEventListener(
-  event: string,
-  callback: (MessageEvent) => void,
-) {
-  evtSource.removeEventListener(event, callback);
+export function removeEventListener(event: string, callback: Listener) {
+  listeners.get(event)?.delete(callback);
 }
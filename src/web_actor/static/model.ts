@@ -0,0 +1,78 @@
+// Generated from the Rust types by `UPDATE_TYPESCRIPT=1 cargo test`. Do not edit.
+
+export type BattleState = { 
+/**
+ * A serialized [`crate::BattleView`]
+ */
+battle: Battle, 
+/**
+ * The character being played, or `None` for spectators
+ */
+character_id: CharacterId | null, };
+
+export type BattleDelta = { "type": "Reset", battle: Battle, } | { "type": "RoundChanged", round: number, rounds_without_health_change: number, } | { "type": "TurnStarted", character_id: CharacterId, } | { "type": "CardPlayed", character_id: CharacterId, card_id: CardId, 
+/**
+ * `None` if the target is out of the viewer's sight
+ */
+target_id: CharacterId | null, } | { "type": "HealthChanged", character_id: CharacterId, health: Health, } | { "type": "CharacterChanged", character: Character, } | { "type": "CharacterHidden", character_id: CharacterId, } | { "type": "BoardChanged", board: Board, } | { "type": "CharacterMoved", character_id: CharacterId, to: GridLocation, } | { "type": "TurnOrderChanged", turn_order: Array<CharacterId>, } | { "type": "HistoryAppended", entries: Array<Array<TemplateEntry<BattleTextEntry>>>, } | { "type": "BattleEnded", outcome: BattleOutcome | null, };
+
+export type Battle = { characters: Record<CharacterId, Character>, introduction: Array<StoryCardEntry> | null, teams: Array<Team>, history: Array<Array<TemplateEntry<BattleTextEntry>>>, round: number, cards: Record<CardId, Card>, default_turn_actions: number, card_pickup: CardPickup, death_rule: DeathRule, board: Board, max_rounds: number | null, stalemate_rounds: number, rounds_without_health_change: number, outcome: BattleOutcome | null, turn_order: Array<CharacterId>, };
+
+export type Character = { id: CharacterId, name: string, race: CharacterRace, hand: Array<CardId> | null, deck: Array<CardId> | null, health: Health, max_health: Health, remaining_actions: number, hand_size: number, image: string | null, movement: number, default_movement: number, loot: Array<CardId> | null, speed: number, action_points: number, vision: number | null, };
+
+export type Board = { grid: Grid<BoardItem>, 
+/**
+ * Whether each tile is currently in sight, indexed by row and then column. `None` if nothing
+ * is hidden from the viewer.
+ */
+visible: Array<Array<boolean>> | null, };
+
+export type Grid<T> = { members: Array<Array<T | null>>, width: number, height: number, };
+
+export type GridLocation = { x: number, y: number, };
+
+export type BoardItem = { "Character": CharacterId } | { "Card": CardId } | { "Remains": CharacterId } | "Wall";
+
+export type Team = { id: TeamId, name: string, objectives: Array<Objective>, };
+
+export type TeamId = number;
+
+export type Objective = "EliminateAll" | { "Defeat": CharacterId } | { "Survive": number } | { "ReachTile": GridLocation } | { "Protect": CharacterId };
+
+export type Victory = { team: TeamId, objective: Objective, };
+
+export type DrawReason = "MutualDefeat" | { "RoundLimit": number } | { "Stalemate": number } | "Unreachable";
+
+export type BattleOutcome = { "Victory": Victory } | { "Draw": DrawReason };
+
+export type CharacterId = number;
+
+export type CharacterRace = "Human" | "Machine";
+
+export type Health = number;
+
+export type Card = { id: CardId, name: string, description: string, flavor: string | null, actions: Array<CardAction>, range: number, };
+
+export type CardId = number;
+
+export type CardAction = { "Damage": { target: Target, amount: U64Range, area: U64Range, } } | { "Heal": { target: Target, amount: U64Range, area: U64Range, } } | { "GainAction": { target: Target, amount: U64Range, } } | { "Move": { target: Target, amount: U64Range, } };
+
+export type Target = "Me" | "Others" | "Any";
+
+export type U64Range = [number, number];
+
+export type CardPickup = "Deck" | "Hand";
+
+export type DeathRule = { body: DeadBody, 
+/**
+ * Whether damage with an area still hits defeated characters. Healing never affects them.
+ */
+area_effects_hit_dead: boolean, };
+
+export type DeadBody = "Remove" | "Corpse" | "Remains";
+
+export type TemplateEntry<TypeId> = { "Text": string } | { "Typed": [TypeId, string] };
+
+export type BattleTextEntry = "Id" | "Attack" | "Damage";
+
+export type StoryCardEntry = { "h1": string } | { "p": string };
@@ -1,34 +1,71 @@
 import { CardId, CharacterId } from "./battle.ts";
-import { Coordinate } from "./utils.ts";
+import { Coordinate, playerPath } from "./utils.ts";
+import { sendRequest } from "./messages.ts";
 
-export async function takeAction(cardId: CardId, targetId: CharacterId) {
-  await fetch("/act", {
+// Returned by the server when an action isn't taken
+export interface ActionError {
+  error:
+    | "unknown_session"
+    | "unknown_player"
+    | "not_your_turn"
+    | "illegal_action"
+    | "bad_request"
+    | "disconnected";
+  message: string;
+}
+
+// Resolves to the reason the action was turned down, if it was. Actions go over
+// the WebSocket when connected and are otherwise posted.
+async function submitAction(
+  path: string,
+  body?: object,
+): Promise<ActionError | null> {
+  const request = sendRequest({ type: path, ...body });
+  if (request !== null) {
+    const error = (await request) as ActionError | null;
+    if (error !== null) {
+      console.warn(`Action not taken: ${error.message}`);
+    }
+    return error;
+  }
+
+  const response = await fetch(playerPath(path), {
     method: "POST",
-    headers: {
-      "Content-Type": "application/json",
-    },
-    body: JSON.stringify({
-      card_id: cardId,
-      target_id: targetId,
-    }),
+    headers: body
+      ? {
+          "Content-Type": "application/json",
+        }
+      : undefined,
+    body: body ? JSON.stringify(body) : undefined,
   });
+  if (response.ok) {
+    return null;
+  }
+  const error: ActionError = await response.json();
+  console.warn(`Action not taken: ${error.message}`);
+  return error;
 }
 
-export async function move(targetId: CharacterId, to: Coordinate) {
-  await fetch("/move", {
-    method: "POST",
-    headers: {
-      "Content-Type": "application/json",
-    },
-    body: JSON.stringify({
-      target_id: targetId,
-      to: to,
-    }),
+export async function takeAction(
+  cardId: CardId,
+  targetId: CharacterId,
+): Promise<ActionError | null> {
+  return await submitAction("act", {
+    card_id: cardId,
+    target_id: targetId,
   });
 }
 
-export async function pass() {
-  await fetch("/pass", {
-    method: "POST",
+export async function move(
+  targetId: CharacterId,
+  to: Coordinate,
+): Promise<ActionError | null> {
+  return await submitAction("move", {
+    target_id: targetId,
+    to: to,
   });
 }
+
+export async function pass(): Promise<ActionError | null> {
+  return await submitAction("pass");
+}
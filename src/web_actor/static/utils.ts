@@ -1,29 +1,39 @@
 import {
-  ActionTarget,
   Battle,
+  BattleOutcome,
   Card,
   CardAction,
   Character,
   CharacterId,
+  Objective,
+  Target,
 } from "./battle";
 
-export function getActionTarget(action: CardAction): ActionTarget {
-  return action["Damage"]?.target ?? action["Heal"]?.target;
+export function getActionTarget(action: CardAction): Target {
+  if ("Damage" in action) {
+    return action.Damage.target;
+  } else if ("Heal" in action) {
+    return action.Heal.target;
+  } else if ("GainAction" in action) {
+    return action.GainAction.target;
+  } else {
+    return action.Move.target;
+  }
 }
 
-export function getCardTarget(card: Card): ActionTarget {
-  let defaultTarget = ActionTarget.Me;
+export function getCardTarget(card: Card): Target {
+  let defaultTarget: Target = "Me";
   for (const action of card.actions) {
     const target = getActionTarget(action);
     switch (target) {
-      case ActionTarget.Any:
+      case "Any":
         // Any is any
-        return ActionTarget.Any;
-      case ActionTarget.Others:
+        return "Any";
+      case "Others":
         // If others found, then it should target others
-        defaultTarget = ActionTarget.Others;
+        defaultTarget = "Others";
         break;
-      case ActionTarget.Me:
+      case "Me":
         break;
     }
   }
@@ -50,6 +60,22 @@ export function assetPath(rawAssetPath: string): string {
   return `ref/${rawAssetPath}`;
 }
 
+// Every player joins with their own token. Without one, the battle is only
+// spectated.
+export function getPlayerToken(): string | null {
+  return new URLSearchParams(window.location.search).get("player");
+}
+
+// Paths are relative to the battle's session. A player's token must accompany
+// each of their requests.
+export function playerPath(path: string): string {
+  const player = getPlayerToken();
+  if (player === null) {
+    return path;
+  }
+  return `${path}?player=${encodeURIComponent(player)}`;
+}
+
 export interface Coordinate {
   x: number;
   y: number;
@@ -64,3 +90,46 @@ export function isAdjacent(a?: Coordinate, b?: Coordinate): boolean {
     (a.y === b.y && (a.x === b.x - 1 || a.x === b.x + 1))
   );
 }
+
+// Characters hidden by fog of war are missing from the battle
+function characterName(battle: Battle, id: CharacterId): string {
+  return battle.characters[id]?.name ?? "An unseen character";
+}
+
+export function describeObjective(
+  battle: Battle,
+  objective: Objective,
+): string {
+  if (objective === "EliminateAll") {
+    return "Every opposing team was defeated";
+  } else if ("Defeat" in objective) {
+    return `${characterName(battle, objective.Defeat)} was defeated`;
+  } else if ("Survive" in objective) {
+    return `Survived ${objective.Survive} rounds`;
+  } else if ("ReachTile" in objective) {
+    return `Reached ${objective.ReachTile.x}, ${objective.ReachTile.y}`;
+  } else {
+    return `${characterName(battle, objective.Protect)} must survive`;
+  }
+}
+
+export function describeOutcome(
+  battle: Battle,
+  outcome: BattleOutcome,
+): string {
+  if ("Victory" in outcome) {
+    const { team: teamId, objective } = outcome.Victory;
+    const team = battle.teams.find((team) => team.id === teamId);
+    return `${team?.name} won. ${describeObjective(battle, objective)}.`;
+  }
+  const reason = outcome.Draw;
+  if (reason === "MutualDefeat") {
+    return "Draw. Every team was defeated.";
+  } else if (reason === "Unreachable") {
+    return "Draw. No one could reach an enemy.";
+  } else if ("RoundLimit" in reason) {
+    return `Draw. The limit of ${reason.RoundLimit} rounds was reached.`;
+  } else {
+    return `Draw. No one was hurt or healed for ${reason.Stalemate} rounds.`;
+  }
+}
@@ -1,70 +1,266 @@
 use super::{
-    handlers::{ArcEventSender, BattleServerEvent, ServerState},
-    server::Server,
+    event_hub::ArcEventHub,
+    handlers::{
+        ActionRejection, ArcServerState, BattleServerEvent, PlayerChannels, ServerState, Session,
+        SessionId,
+    },
+    server::{Server, ServerConfig},
+};
+use crate::{
+    ActionError, ActionResult, Actor, Battle, BattleDelta, BattleObserver, BattleOutcome,
+    BattleUpdate, BattleView, CharacterId, DeltaTracker,
 };
-use crate::{ActionResult, Actor, Battle, CharacterId};
-use actix_web_lab::sse;
 use async_trait::async_trait;
+use rand::{distributions::Alphanumeric, Rng};
 use serde::Serialize;
-use std::{path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 use tokio::sync::{
-    mpsc::{channel, error::SendError, Receiver},
+    mpsc::{channel, Receiver},
     Mutex,
 };
+use ts_rs::TS;
 
-#[derive(Serialize)]
-struct BattleState<'battle> {
-    battle: &'battle Battle,
-    character_id: CharacterId,
+/// The full battle, which subscribers start from before applying deltas. Also what the `state`
+/// endpoint returns.
+#[derive(Serialize, TS)]
+pub struct BattleState<'view> {
+    /// A serialized [`crate::BattleView`]
+    #[ts(type = "Battle")]
+    pub battle: &'view serde_json::Value,
+    /// The character being played, or `None` for spectators
+    pub character_id: Option<CharacterId>,
+}
+
+impl BattleState<'_> {
+    /// The battle as the viewer playing `character_id` sees it, along with its serialized state
+    fn view(battle: &Battle, character_id: Option<CharacterId>) -> (serde_json::Value, String) {
+        let viewer = character_id.and_then(|id| battle.get_team_for_character(&id));
+        let view = serde_json::to_value(BattleView::new(battle, viewer)).unwrap();
+        let state = serde_json::to_string(&BattleState {
+            battle: &view,
+            character_id,
+        })
+        .unwrap();
+        (view, state)
+    }
+}
+
+const PLAYER_TOKEN_LENGTH: usize = 16;
+
+/// The web server shared by every battle being hosted. Each battle is reached through its own
+/// session and requests are routed to the right actor by the player token each player is given.
+pub struct WebActorServer {
+    server: Server<ServerState>,
+    state: ArcServerState,
+}
+
+impl WebActorServer {
+    pub fn new(config: &ServerConfig) -> Result<Arc<Self>, std::io::Error> {
+        let state = ArcServerState::default();
+        let server = Server::new(state.clone(), config)?;
+        Ok(Arc::new(Self { server, state }))
+    }
+
+    /// The address the server is bound to, including the real port if port 0 was requested
+    pub fn address(&self) -> SocketAddr {
+        self.server.address
+    }
+
+    /// Starts hosting a new battle. If `id` is already taken, a numeric suffix is added.
+    pub async fn open_session(
+        self: &Arc<Self>,
+        id: &str,
+        title: &str,
+        description: &str,
+    ) -> WebSession {
+        let mut state = self.state.lock().await;
+        let mut session_id = id.to_string();
+        let mut suffix = 1;
+        while state.sessions.contains_key(&session_id) {
+            suffix += 1;
+            session_id = format!("{id}-{suffix}");
+        }
+        state.sessions.insert(
+            session_id.clone(),
+            Session {
+                title: title.to_string(),
+                description: description.to_string(),
+                players: HashMap::new(),
+                spectators: ArcEventHub::default(),
+                finished: false,
+            },
+        );
+        WebSession {
+            server: self.clone(),
+            id: session_id,
+        }
+    }
 }
+
+/// A handle to one battle hosted by a [`WebActorServer`]
+#[derive(Clone)]
+pub struct WebSession {
+    server: Arc<WebActorServer>,
+    id: SessionId,
+}
+
+impl WebSession {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The link players open to play in this battle, without their player token
+    pub fn url(&self) -> String {
+        format!(
+            "http://{}/sessions/{}/index.html",
+            self.server.address(),
+            self.id
+        )
+    }
+
+    /// Creates an observer that keeps the battle's players and spectators up to date
+    pub fn observer(&self) -> Box<dyn BattleObserver> {
+        Box::new(SessionObserver {
+            session: self.clone(),
+            trackers: Mutex::default(),
+        })
+    }
+
+    /// Lets anyone who looks before the battle starts see how it begins
+    pub async fn show(&self, battle: &Battle) {
+        for (character_id, events) in self.viewers(battle).await {
+            let (_, state) = BattleState::view(battle, character_id);
+            events.lock().await.set_snapshot("battle_state", state);
+        }
+    }
+
+    /// Every player along with spectators, under `None`, and where to send them events. Also keeps
+    /// what each player may do up to date.
+    async fn viewers(&self, battle: &Battle) -> Vec<(Option<CharacterId>, ArcEventHub)> {
+        // Released before returning so requests aren't held up while viewers are sent events
+        let mut state = self.server.state.lock().await;
+        let Some(session) = state.sessions.get_mut(&self.id) else {
+            return vec![];
+        };
+        for channels in session.players.values_mut() {
+            channels.legal_actions = battle.legal_actions(&channels.character_id);
+        }
+        std::iter::once((None, session.spectators.clone()))
+            .chain(
+                session
+                    .players
+                    .values()
+                    .map(|channels| (Some(channels.character_id), channels.events.clone())),
+            )
+            .collect()
+    }
+
+    /// Marks the battle as over so it no longer shows up in the lobby
+    async fn finish(&self) {
+        if let Some(session) = self.server.state.lock().await.sessions.get_mut(&self.id) {
+            session.finished = true;
+        }
+    }
+}
+
+/// Broadcasts what changed in the battle to each player and spectator as they are allowed to see it
+struct SessionObserver {
+    session: WebSession,
+    /// What each player, or spectators under `None`, were last sent
+    trackers: Mutex<HashMap<Option<CharacterId>, DeltaTracker>>,
+}
+
+#[async_trait]
+impl BattleObserver for SessionObserver {
+    async fn on_update(&self, battle: &Battle, update: &BattleUpdate) {
+        let viewers = self.session.viewers(battle).await;
+        let mut trackers = self.trackers.lock().await;
+        for (character_id, events) in viewers {
+            let (view, snapshot) = BattleState::view(battle, character_id);
+            let deltas = trackers
+                .entry(character_id)
+                .or_default()
+                .track(view, update);
+
+            let mut events = events.lock().await;
+            match deltas.first() {
+                // Viewers starting over are sent the full state, which also says who they're playing
+                Some(BattleDelta::Reset { .. }) => {
+                    events.broadcast("battle_state", snapshot.clone())
+                }
+                Some(_) => {
+                    events.broadcast("battle_deltas", serde_json::to_string(&deltas).unwrap())
+                }
+                None => {}
+            }
+            events.set_snapshot("battle_state", snapshot);
+        }
+    }
+}
+
 pub struct WebActor {
     character_id: CharacterId,
-    #[allow(dead_code)] // Required to stay alive during lifetime of WebActor
-    server: Server<ServerState>,
-    event_tx: ArcEventSender,
+    session: WebSession,
     action_rx: Arc<Mutex<Receiver<BattleServerEvent>>>,
+    acting: Arc<AtomicBool>,
 }
 
 impl WebActor {
-    pub async fn new(
-        character_id: CharacterId,
-        additional_asset_directory: Option<&Path>,
-    ) -> Result<Self, std::io::Error> {
+    pub async fn new(character_id: CharacterId, session: WebSession) -> Self {
         let (action_tx, action_rx) = channel(1);
+        let acting = Arc::new(AtomicBool::new(false));
 
-        let event_tx = ArcEventSender::default();
-
-        let server = Server::new(
-            Arc::new(Mutex::new(ServerState {
-                event_tx: event_tx.clone(),
-                action_tx: action_tx.clone(),
-            })),
-            additional_asset_directory,
-        )?;
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(PLAYER_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        println!(
+            "Player for character {} can join at {}?player={}",
+            character_id,
+            session.url(),
+            token
+        );
+        if let Some(server_session) = session
+            .server
+            .state
+            .lock()
+            .await
+            .sessions
+            .get_mut(&session.id)
+        {
+            server_session.players.insert(
+                token,
+                PlayerChannels {
+                    character_id,
+                    events: ArcEventHub::default(),
+                    action_tx,
+                    acting: acting.clone(),
+                    legal_actions: vec![],
+                    claimed: false,
+                },
+            );
+        }
 
-        Ok(Self {
+        Self {
             character_id,
-            server,
-            event_tx,
+            session,
             action_rx: Arc::new(Mutex::new(action_rx)),
-        })
+            acting,
+        }
     }
 
-    async fn send_battle_state(&self, battle: &Battle) -> Result<(), SendError<sse::Event>> {
-        if let Some(sender) = self.event_tx.lock().await.as_ref() {
-            sender
-                .send(
-                    sse::Data::new_json(BattleState {
-                        battle,
-                        character_id: self.character_id,
-                    })
-                    .unwrap()
-                    .event("battle_state")
-                    .into(),
-                )
-                .await?;
-        }
-        Ok(())
+    /// The address of the server this actor's player connects to
+    pub fn address(&self) -> SocketAddr {
+        self.session.server.address()
     }
 }
 
@@ -75,23 +271,138 @@ impl Actor for WebActor {
     }
 
     async fn act(&self, battle: &Battle) -> ActionResult {
-        self.send_battle_state(battle).await?;
+        let mut action_rx = self.action_rx.lock().await;
+        // Turn away anything sent while the battle wasn't waiting on this player
+        while let Ok(BattleServerEvent::Action(_, reply)) = action_rx.try_recv() {
+            let _ = reply.send(Err(ActionRejection::NotYourTurn));
+        }
+
+        self.acting.store(true, Ordering::SeqCst);
         loop {
-            match self.action_rx.lock().await.recv().await {
-                Some(BattleServerEvent::BattleRequest) => {
-                    self.send_battle_state(battle).await?;
+            match action_rx.recv().await {
+                Some(BattleServerEvent::Action(action, reply)) => {
+                    match battle.check_action(&self.character_id, &action) {
+                        Ok(()) => {
+                            self.acting.store(false, Ordering::SeqCst);
+                            let _ = reply.send(Ok(()));
+                            return Ok(action);
+                        }
+                        Err(reason) => {
+                            let _ = reply.send(Err(ActionRejection::Illegal(reason)));
+                        }
+                    }
                 }
-                Some(BattleServerEvent::Action(action)) => {
-                    return action;
+                // The server is no longer hosting this battle so nothing can be sent
+                None => {
+                    self.acting.store(false, Ordering::SeqCst);
+                    return Err(ActionError::Exit(ExitCode::FAILURE));
                 }
-                None => {}
             }
         }
     }
 
-    async fn on_game_over(&self, battle: &Battle) {
-        self.send_battle_state(battle)
-            .await
-            .expect("Failed to send game over state");
+    async fn on_game_over(&self, _battle: &Battle, _outcome: &BattleOutcome) {
+        self.session.finish().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebActorServer;
+    use crate::{
+        web_actor::ServerConfig, Battle, BattleFormat, DefaultRandomProvider, Diagnostics,
+    };
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+    };
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn local_server() -> std::sync::Arc<WebActorServer> {
+        WebActorServer::new(&ServerConfig {
+            host: "127.0.0.1".into(),
+            port: 0,
+            asset_directory: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_address_of_any_port() {
+        let server = local_server();
+        let address = server.address();
+        assert_ne!(address.port(), 0, "The port the OS picked is reported");
+
+        let response = get(address, "/lobby");
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("[]"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_state_before_the_battle_starts() -> Result<(), Diagnostics> {
+        let battle_json = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 3, "height": 1 },
+            "cards": [],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member A",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [0, 0]
+                        }
+                    ]
+                },
+                {
+                    "name": "Team B",
+                    "members": [
+                        {
+                            "name": "Member B",
+                            "race": "Human",
+                            "base_health": 5,
+                            "cards": [],
+                            "location": [2, 0]
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let server = local_server();
+        let _battle = Battle::deserialize_in_session(
+            battle_json,
+            BattleFormat::Json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+            &server,
+            "example",
+        )
+        .await?;
+
+        let response = get(server.address(), "/sessions/example/state");
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let state: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(state["character_id"], serde_json::Value::Null);
+        assert_eq!(state["battle"]["round"], 0);
+        assert_eq!(state["battle"]["characters"].as_object().unwrap().len(), 2);
+        Ok(())
     }
 }
@@ -0,0 +1,307 @@
+use super::{
+    event_hub::{EventId, HubEvent},
+    handlers::{
+        submit_action, viewer_events, ArcServerState, ErrorCode, PlayerToken, RequestError,
+        SessionId,
+    },
+};
+use crate::{Action, CardId, CharacterId, GridLocation};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, Session};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::VecDeque;
+use tokio::sync::mpsc::Receiver;
+
+#[derive(Deserialize)]
+struct WebSocketParams {
+    /// Views the battle as a spectator if missing. Spectators can't send actions.
+    player: Option<PlayerToken>,
+    /// Resumes after this event, like `Last-Event-ID` does for server-sent events
+    last_event_id: Option<EventId>,
+}
+
+#[derive(Deserialize)]
+struct MoveDestination {
+    x: usize,
+    y: usize,
+}
+
+/// Actions sent by clients, with the same fields as the equivalent HTTP requests
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ActionRequest {
+    Act {
+        card_id: usize,
+        target_id: usize,
+    },
+    Move {
+        target_id: usize,
+        to: MoveDestination,
+    },
+    Pass,
+}
+
+impl From<ActionRequest> for Action {
+    fn from(request: ActionRequest) -> Self {
+        match request {
+            ActionRequest::Act { card_id, target_id } => {
+                Action::Act(CardId::new(card_id), CharacterId::new(target_id))
+            }
+            ActionRequest::Move { target_id, to } => Action::Move(
+                CharacterId::new(target_id),
+                GridLocation { x: to.x, y: to.y },
+            ),
+            ActionRequest::Pass => Action::Pass,
+        }
+    }
+}
+
+/// A message from the client. The id is chosen by the client and echoed back in the response.
+#[derive(Deserialize)]
+struct ClientMessage {
+    id: u64,
+    #[serde(flatten)]
+    action: ActionRequest,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'message> {
+    /// The same events sent over server-sent events
+    Event {
+        id: EventId,
+        name: &'static str,
+        data: &'message RawValue,
+    },
+    /// The action was accepted. Any events it causes are sent after this.
+    Ack { request_id: u64 },
+    Error {
+        /// `None` if the message couldn't be read well enough to find its id
+        request_id: Option<u64>,
+        #[serde(flatten)]
+        error: RequestError,
+    },
+}
+
+/// Carries battle events to the client and actions back to the battle over a single connection,
+/// so responses to actions are always ordered before the events they cause
+#[get("/ws")]
+async fn handle_websocket(
+    request: HttpRequest,
+    body: web::Payload,
+    session_id: web::Path<SessionId>,
+    params: web::Query<WebSocketParams>,
+    state: web::Data<ArcServerState>,
+) -> actix_web::Result<HttpResponse> {
+    let params = params.into_inner();
+    let events = match viewer_events(&mut *state.lock().await, &session_id, &params.player) {
+        Ok(events) => events,
+        Err(error) => return Ok(error.into_response()),
+    };
+    let (response, ws, messages) = actix_ws::handle(&request, body)?;
+    let rx = events.lock().await.subscribe(params.last_event_id);
+
+    actix_web::rt::spawn(run_websocket(
+        ws,
+        messages.aggregate_continuations(),
+        rx,
+        state.get_ref().clone(),
+        session_id.into_inner(),
+        params.player,
+    ));
+    Ok(response)
+}
+
+fn event_message(event: HubEvent) -> String {
+    let data = RawValue::from_string(event.data).unwrap();
+    serde_json::to_string(&ServerMessage::Event {
+        id: event.id,
+        name: event.name,
+        data: &data,
+    })
+    .unwrap()
+}
+
+async fn run_websocket(
+    mut ws: Session,
+    mut messages: AggregatedMessageStream,
+    mut rx: Receiver<HubEvent>,
+    state: ArcServerState,
+    session_id: SessionId,
+    player: Option<PlayerToken>,
+) {
+    // Events that came in while an action was being taken, held back until it is acknowledged
+    let mut held_back: VecDeque<HubEvent> = VecDeque::new();
+    loop {
+        if let Some(event) = held_back.pop_front() {
+            if ws.text(event_message(event)).await.is_err() {
+                return;
+            }
+            continue;
+        }
+        let message = tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => event_message(event),
+                None => break,
+            },
+            message = messages.recv() => match message {
+                Some(Ok(AggregatedMessage::Text(text))) => {
+                    // Keep receiving events while waiting so the subscription doesn't fall behind
+                    let response = respond(&state, &session_id, &player, &text);
+                    tokio::pin!(response);
+                    let response = loop {
+                        tokio::select! {
+                            response = &mut response => break response,
+                            Some(event) = rx.recv() => held_back.push_back(event),
+                        }
+                    };
+                    serde_json::to_string(&response).unwrap()
+                }
+                Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                    if ws.pong(&bytes).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                Some(Ok(AggregatedMessage::Binary(_) | AggregatedMessage::Pong(_))) => continue,
+                Some(Ok(AggregatedMessage::Close(_)) | Err(_)) | None => break,
+            },
+        };
+        if ws.text(message).await.is_err() {
+            return;
+        }
+    }
+    let _ = ws.close(None).await;
+}
+
+/// Takes the action in a client's message, describing how it went
+async fn respond(
+    state: &ArcServerState,
+    session_id: &SessionId,
+    player: &Option<PlayerToken>,
+    text: &str,
+) -> ServerMessage<'static> {
+    let message: ClientMessage = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(err) => {
+            return ServerMessage::Error {
+                request_id: serde_json::from_str::<serde_json::Value>(text)
+                    .ok()
+                    .and_then(|value| value["id"].as_u64()),
+                error: RequestError::new(ErrorCode::BadRequest, err.to_string()),
+            }
+        }
+    };
+    let Some(player) = player else {
+        return ServerMessage::Error {
+            request_id: Some(message.id),
+            error: RequestError::new(ErrorCode::UnknownPlayer, "Spectators can't take actions"),
+        };
+    };
+    match submit_action(state, session_id, player, message.action.into()).await {
+        Ok(()) => ServerMessage::Ack {
+            request_id: message.id,
+        },
+        Err(error) => ServerMessage::Error {
+            request_id: Some(message.id),
+            error,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{event_message, respond};
+    use crate::web_actor::{
+        event_hub::HubEvent,
+        handlers::{
+            tests::{allow_only_passing, session},
+            ArcServerState,
+        },
+    };
+    use serde_json::{json, Value};
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_respond() {
+        let state = ArcServerState::default();
+        let (session, mut receivers) = session(&["acting"]);
+        session.players["acting"]
+            .acting
+            .store(true, Ordering::SeqCst);
+        state.lock().await.sessions.insert("battle".into(), session);
+        allow_only_passing(receivers.remove(0));
+        let respond = |player: Option<&str>, text: &str| {
+            let state = state.clone();
+            let player = player.map(String::from);
+            let text = text.to_string();
+            async move {
+                let response = respond(&state, &"battle".into(), &player, &text).await;
+                serde_json::to_value(response).unwrap()
+            }
+        };
+
+        assert_eq!(
+            respond(Some("acting"), r#"{ "id": 1, "type": "pass" }"#).await,
+            json!({ "type": "ack", "request_id": 1 })
+        );
+        assert_eq!(
+            respond(
+                Some("acting"),
+                r#"{ "id": 2, "type": "act", "card_id": 0, "target_id": 1 }"#
+            )
+            .await,
+            json!({
+                "type": "error",
+                "request_id": 2,
+                "error": "illegal_action",
+                "message": "Out of range"
+            })
+        );
+        assert_eq!(
+            respond(None, r#"{ "id": 3, "type": "pass" }"#).await,
+            json!({
+                "type": "error",
+                "request_id": 3,
+                "error": "unknown_player",
+                "message": "Spectators can't take actions"
+            })
+        );
+        assert_eq!(
+            respond(Some("unknown"), r#"{ "id": 4, "type": "pass" }"#).await["error"],
+            "unknown_player"
+        );
+
+        let response = respond(Some("acting"), r#"{ "id": 5, "type": "fly" }"#).await;
+        assert_eq!(
+            (&response["request_id"], &response["error"]),
+            (&json!(5), &json!("bad_request")),
+            "The id is still found in messages that can't be read"
+        );
+        let response = respond(Some("acting"), "pass").await;
+        assert_eq!(
+            (&response["request_id"], &response["error"]),
+            (&Value::Null, &json!("bad_request"))
+        );
+    }
+
+    #[test]
+    fn test_event_message() {
+        let message = event_message(HubEvent {
+            id: 3,
+            name: "battle_deltas",
+            data: r#"[{"type":"TurnStarted","character_id":0}]"#.into(),
+        });
+        assert_eq!(
+            serde_json::from_str::<Value>(&message).unwrap(),
+            json!({
+                "type": "event",
+                "id": 3,
+                "name": "battle_deltas",
+                "data": [{ "type": "TurnStarted", "character_id": 0 }]
+            }),
+            "Event data is sent as JSON rather than a string of it"
+        );
+    }
+}
@@ -1,7 +1,8 @@
 #[macro_export]
 macro_rules! DeclareWrappedType {
     ($struct_name:ident, $field_name:ident, $field_type:ty) => {
-        #[derive(PartialEq, Copy, Clone, Eq, Hash, Debug, PartialOrd, Ord)]
+        #[derive(PartialEq, Copy, Clone, Eq, Hash, Debug, PartialOrd, Ord, ts_rs::TS)]
+        #[ts(type = "number")]
         pub struct $struct_name {
             pub $field_name: $field_type,
         }
@@ -8,6 +8,8 @@ const isProd = !isDev;
 
 export default defineConfig({
     plugins: [react({ minify: isProd })],
+    // Battles are served under their own session path so assets must be resolved relatively
+    base: "./",
     build: {
         minify: isProd ? "esbuild" : false,
         sourcemap: true,